@@ -0,0 +1,8 @@
+CREATE TABLE indexer_checkpoints
+(
+    -- Arbitrary name of the indexer instance, see `--indexer-id`
+    indexer_id                  text           PRIMARY KEY,
+    -- The last block which was fully stored by the indexer instance
+    last_processed_block_height numeric(20, 0) NOT NULL,
+    updated_at                  timestamptz    NOT NULL DEFAULT now()
+);
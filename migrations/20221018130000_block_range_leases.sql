@@ -0,0 +1,14 @@
+-- Ranges of blocks shared between the indexers running in coordinator mode
+CREATE TABLE block_range_leases
+(
+    range_start                 numeric(20, 0) PRIMARY KEY,
+    -- Inclusive
+    range_end                   numeric(20, 0) NOT NULL,
+    -- The last worker which claimed the range
+    worker_id                   text,
+    -- The last block of the range which was fully stored, used to continue the abandoned range
+    last_processed_block_height numeric(20, 0),
+    -- The range could be re-claimed by another worker after this moment
+    leased_until                timestamptz,
+    completed_at                timestamptz
+);
@@ -0,0 +1,12 @@
+-- All the contracts which have ever produced FT/NFT events
+CREATE TABLE contracts
+(
+    contract_account_id                 text           PRIMARY KEY,
+    -- The standard of the first event, e.g. FT_NEP141, FT_LEGACY, NFT_NEP171
+    standard                            text           NOT NULL,
+    first_event_at_timestamp            numeric(20, 0) NOT NULL,
+    first_event_at_block_height         numeric(20, 0) NOT NULL,
+    -- Filled when the balances collected from the events do not match the balances from RPC
+    inconsistency_found_at_timestamp    numeric(20, 0),
+    inconsistency_found_at_block_height numeric(20, 0)
+);
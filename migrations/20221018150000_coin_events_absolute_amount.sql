@@ -0,0 +1,4 @@
+-- Balance of the affected account after the event.
+-- NULL for the rows stored before this column was added and for the contracts without `ft_balance_of`
+ALTER TABLE coin_events
+    ADD COLUMN absolute_amount numeric(40, 0);
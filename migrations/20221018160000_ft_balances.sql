@@ -0,0 +1,12 @@
+-- Current FT balances, the sum of all the successful `coin_events.delta_amount` of the account
+CREATE TABLE ft_balances
+(
+    contract_account_id text           NOT NULL,
+    account_id          text           NOT NULL,
+    balance             numeric(40, 0) NOT NULL,
+    -- The last event included into the balance, the events up to this one are never applied again
+    last_event_index    numeric(38, 0) NOT NULL,
+    PRIMARY KEY (contract_account_id, account_id)
+);
+
+CREATE INDEX ft_balances_account_id_idx ON ft_balances (account_id);
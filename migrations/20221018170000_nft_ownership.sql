@@ -0,0 +1,12 @@
+-- Current owners of NFTs, taken from the last successful MINT/TRANSFER/BURN in `nft_events`
+CREATE TABLE nft_ownership
+(
+    contract_account_id text           NOT NULL,
+    token_id            text           NOT NULL,
+    -- Null if the token is burnt. The row is kept, so the older events never bring the token back
+    owner_account_id    text,
+    last_event_index    numeric(38, 0) NOT NULL,
+    PRIMARY KEY (contract_account_id, token_id)
+);
+
+CREATE INDEX nft_ownership_owner_account_id_idx ON nft_ownership (owner_account_id);
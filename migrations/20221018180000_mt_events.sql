@@ -0,0 +1,27 @@
+-- NEP-245 multi-token events, one row for each token of the event
+CREATE TABLE mt_events
+(
+    event_index           numeric(38, 0) PRIMARY KEY,
+    standard              text           NOT NULL,
+    receipt_id            text           NOT NULL,
+    block_height          numeric(20, 0) NOT NULL,
+    block_timestamp       numeric(20, 0) NOT NULL,
+    -- account_id of the contract itself. In a simple words, it's the owner/creator of MT contract
+    contract_account_id   text           NOT NULL,
+    token_id              text           NOT NULL,
+    cause                 text           NOT NULL,
+    status                text           NOT NULL,
+    -- Previous owner of the tokens. Null if we have mt_event_kind 'MINT'.
+    old_owner_account_id  text,
+    -- New owner of the tokens. Null if we have mt_event_kind 'BURN'.
+    new_owner_account_id  text,
+    authorized_account_id text,
+    -- Number of the tokens with token_id moved by the event
+    amount                numeric(40, 0) NOT NULL,
+    event_memo            text
+);
+
+CREATE INDEX mt_events_block_height_idx ON mt_events (block_height);
+CREATE INDEX mt_events_receipt_id_idx ON mt_events (receipt_id);
+CREATE INDEX mt_events_old_owner_account_id_idx ON mt_events (old_owner_account_id);
+CREATE INDEX mt_events_new_owner_account_id_idx ON mt_events (new_owner_account_id);
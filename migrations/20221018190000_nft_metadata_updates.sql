@@ -0,0 +1,18 @@
+-- NEP-171 metadata update events: the cached metadata of the contract or the token should be refreshed
+CREATE TABLE nft_metadata_updates
+(
+    event_index         numeric(38, 0) PRIMARY KEY,
+    standard            text           NOT NULL,
+    receipt_id          text           NOT NULL,
+    block_height        numeric(20, 0) NOT NULL,
+    block_timestamp     numeric(20, 0) NOT NULL,
+    contract_account_id text           NOT NULL,
+    -- Null if the metadata of the contract itself is updated ('CONTRACT_METADATA_UPDATE')
+    token_id            text,
+    cause               text           NOT NULL,
+    status              text           NOT NULL,
+    event_memo          text
+);
+
+CREATE INDEX nft_metadata_updates_block_height_idx ON nft_metadata_updates (block_height);
+CREATE INDEX nft_metadata_updates_contract_account_id_token_id_idx ON nft_metadata_updates (contract_account_id, token_id);
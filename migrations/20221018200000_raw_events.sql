@@ -0,0 +1,22 @@
+-- All the well-formed NEP-297 event logs, including the standards we do not parse yet
+CREATE TABLE raw_events
+(
+    event_index         numeric(38, 0) PRIMARY KEY,
+    standard            text           NOT NULL,
+    version             text           NOT NULL,
+    event               text           NOT NULL,
+    -- Null if the event has no data
+    data                jsonb,
+    receipt_id          text           NOT NULL,
+    block_height        numeric(20, 0) NOT NULL,
+    block_timestamp     numeric(20, 0) NOT NULL,
+    contract_account_id text           NOT NULL,
+    status              text           NOT NULL,
+    -- Position of the log in the logs of the receipt execution outcome
+    log_index           integer        NOT NULL
+);
+
+CREATE INDEX raw_events_block_height_idx ON raw_events (block_height);
+CREATE INDEX raw_events_receipt_id_idx ON raw_events (receipt_id);
+CREATE INDEX raw_events_standard_event_idx ON raw_events (standard, event);
+CREATE INDEX raw_events_contract_account_id_idx ON raw_events (contract_account_id);
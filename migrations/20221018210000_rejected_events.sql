@@ -0,0 +1,19 @@
+-- Event logs which were not stored: malformed ones and the ones with invalid values (e.g. AccountId).
+-- The calls of legacy contracts which move the tokens in the way we can't collect are also here
+CREATE TABLE rejected_events
+(
+    receipt_id          text           NOT NULL,
+    -- Position of the log in the logs of the receipt execution outcome.
+    -- Negative for the legacy calls: -1 for the first action of the receipt, -2 for the second one, etc.
+    log_index           integer        NOT NULL,
+    block_height        numeric(20, 0) NOT NULL,
+    block_timestamp     numeric(20, 0) NOT NULL,
+    contract_account_id text           NOT NULL,
+    -- The action as JSON for the legacy calls
+    log                 text           NOT NULL,
+    error               text           NOT NULL,
+    PRIMARY KEY (receipt_id, log_index)
+);
+
+CREATE INDEX rejected_events_block_height_idx ON rejected_events (block_height);
+CREATE INDEX rejected_events_contract_account_id_idx ON rejected_events (contract_account_id);
@@ -0,0 +1,17 @@
+-- Version of NEP-297 event the row is built from.
+-- NULL for the legacy events without the version and for the rows stored before this column was added
+ALTER TABLE coin_events
+    ADD COLUMN event_version text,
+    ADD COLUMN unsupported_event_version boolean NOT NULL DEFAULT false;
+
+ALTER TABLE nft_events
+    ADD COLUMN event_version text,
+    ADD COLUMN unsupported_event_version boolean NOT NULL DEFAULT false;
+
+ALTER TABLE mt_events
+    ADD COLUMN event_version text,
+    ADD COLUMN unsupported_event_version boolean NOT NULL DEFAULT false;
+
+ALTER TABLE nft_metadata_updates
+    ADD COLUMN event_version text,
+    ADD COLUMN unsupported_event_version boolean NOT NULL DEFAULT false;
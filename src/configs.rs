@@ -1,6 +1,8 @@
 use clap::Parser;
 use tracing_subscriber::EnvFilter;
 
+const DB_CONNECTIONS_MARGIN: usize = 8;
+
 /// NEAR Indexer for Explorer
 /// Watches for stream of blocks from the chain
 #[derive(Parser, Debug)]
@@ -16,38 +18,282 @@ pub(crate) struct Opts {
     /// Enabled Indexer for Explorer debug level of logs
     #[clap(long)]
     pub debug: bool,
-    /// Block height to start the stream from
+    /// Block height to start the stream from. Takes precedence over the stored checkpoint
     #[clap(long, short, env)]
-    pub start_block_height: u64,
+    pub start_block_height: Option<u64>,
+    /// How to choose the start block if `start_block_height` is not provided:
+    /// `from-block` requires the explicit height, `from-interruption` resumes from the checkpoint
+    #[clap(long, env, default_value = "from-block")]
+    pub start_mode: StartMode,
+    /// Block height to stop the stream at (inclusive). The process exits when the range is indexed
+    #[clap(long, env, conflicts_with = "blocks-count")]
+    pub end_block_height: Option<u64>,
+    /// Number of blocks to index, an alternative to `end_block_height`
+    #[clap(long, env)]
+    pub blocks_count: Option<u64>,
+    /// Number of blocks processed concurrently. The checkpoint still moves only over
+    /// the blocks which are stored together with all the previous ones
+    #[clap(long, env, default_value = "1")]
+    pub concurrency: std::num::NonZeroUsize,
+    /// Name of the indexer instance, used as a key for storing the checkpoint
+    #[clap(long, env, default_value = "indexer_events")]
+    pub indexer_id: String,
+    /// Split the range between `start_block_height` and `end_block_height` into leases
+    /// shared with all the other indexers running in this mode over the same DB.
+    /// `ft_balances` is not updated, run `rebuild-ft-balances` when all the ranges are completed
+    #[clap(long)]
+    pub coordinator: bool,
+    /// Number of blocks in one lease of coordinator mode
+    #[clap(long, env, default_value_t = 10_000)]
+    pub range_size: u64,
+    /// Seconds after which the lease of the silent worker could be re-claimed by another one
+    #[clap(long, env, default_value_t = 300)]
+    pub lease_timeout_secs: u64,
     #[clap(long, short, env)]
     pub near_archival_rpc_url: String,
-    // Chain ID: testnet or mainnet, used for NEAR Lake initialization
+    /// Chain ID: mainnet, testnet or any custom label like localnet.
+    /// Chooses NEAR Lake bucket for mainnet/testnet and the accounts of legacy contracts
     #[clap(long, env)]
     pub chain_id: String,
+    /// Custom NEAR Lake bucket name, required if CHAIN_ID is neither mainnet nor testnet
+    #[clap(long, env)]
+    pub lake_s3_bucket_name: Option<String>,
+    /// Custom NEAR Lake bucket region, required together with LAKE_S3_BUCKET_NAME
+    #[clap(long, env)]
+    pub lake_s3_region_name: Option<String>,
+    /// Custom S3-compatible endpoint, e.g. MinIO: http://localhost:9000
+    #[clap(long, env)]
+    pub lake_s3_endpoint: Option<String>,
+    /// How to write the rows: `insert` with multi-row INSERT queries,
+    /// `copy` with COPY to the temporary table, which is faster for the blocks with many events
+    #[clap(long, env, default_value = "insert")]
+    pub insert_method: InsertMethod,
+    /// Fill `absolute_amount` of `coin_events` with the balances after each event.
+    /// The balances of the accounts seen for the first time are taken from `ft_balance_of` of the archival RPC
+    #[clap(long, env)]
+    pub fill_absolute_amounts: bool,
+    /// Check the balances from `coin_events` against `ft_balance_of` from the archival RPC
+    /// and mark the inconsistent contracts in `contracts` table
+    #[clap(long, env)]
+    pub reconcile_balances: bool,
+    /// What to do with the events of the version we do not support:
+    /// `strict` puts them to `rejected_events`, `lenient` stores them with `unsupported_event_version` flag
+    #[clap(long, env, default_value = "strict")]
+    pub event_version_mode: EventVersionMode,
+    /// Seconds to wait for in-flight blocks to be stored after SIGINT/SIGTERM
+    #[clap(long, env, default_value_t = 20)]
+    pub shutdown_timeout_secs: u64,
+    /// Config of the legacy FT contracts, see `src/db_adapters/coin/legacy/DOC.md`.
+    /// The one embedded to the binary is used if not provided
+    #[clap(long, env)]
+    pub legacy_ft_contracts_config: Option<std::path::PathBuf>,
+    /// Config of the legacy NFT contracts, see `src/db_adapters/nft/legacy/DOC.md`.
+    /// The one embedded to the binary is used if not provided
+    #[clap(long, env)]
+    pub legacy_nft_contracts_config: Option<std::path::PathBuf>,
+    /// Read the blocks from the local directory with NEAR Lake bucket layout instead of S3
+    #[clap(long, env)]
+    pub blocks_dir: Option<std::path::PathBuf>,
     /// Port to enable metrics/health service
     #[clap(long, short, env, default_value_t = 3000)]
     pub port: u16,
+    /// `/ready` reports failure if no block was stored for this number of seconds
+    #[clap(long, env, default_value_t = 300)]
+    pub max_block_lag_secs: u64,
+    /// Exit with non-zero status when indexing fails instead of serving metrics until the shutdown
+    #[clap(long, env)]
+    pub exit_on_failure: bool,
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Maintenance commands, the indexer is not started if any of them is given
+#[derive(clap::Subcommand, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Command {
+    /// Recompute `ft_balances` from all the stored `coin_events` and exit.
+    /// Stop the indexers writing to the same DB before running it
+    RebuildFtBalances,
+    /// Recompute `nft_ownership` from all the stored `nft_events` and exit.
+    /// Stop the indexers writing to the same DB before running it
+    RebuildNftOwnership,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StartMode {
+    FromBlock,
+    FromInterruption,
+}
+
+impl std::str::FromStr for StartMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "from-block" => Ok(StartMode::FromBlock),
+            "from-interruption" => Ok(StartMode::FromInterruption),
+            invalid_mode => Err(format!(
+                "Invalid start mode: `{}`. Try `from-block` or `from-interruption`",
+                invalid_mode
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InsertMethod {
+    Insert,
+    Copy,
+}
+
+impl std::str::FromStr for InsertMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "insert" => Ok(InsertMethod::Insert),
+            "copy" => Ok(InsertMethod::Copy),
+            invalid_method => Err(format!(
+                "Invalid insert method: `{}`. Try `insert` or `copy`",
+                invalid_method
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EventVersionMode {
+    Strict,
+    Lenient,
+}
+
+impl std::str::FromStr for EventVersionMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(EventVersionMode::Strict),
+            "lenient" => Ok(EventVersionMode::Lenient),
+            invalid_mode => Err(format!(
+                "Invalid event version mode: `{}`. Try `strict` or `lenient`",
+                invalid_mode
+            )),
+        }
+    }
 }
 
 impl Opts {
+    // The explicit start_block_height always wins;
+    // otherwise we continue from the block next to the stored checkpoint
+    pub async fn get_start_block_height(
+        &self,
+        pool: &sqlx::Pool<sqlx::Postgres>,
+    ) -> anyhow::Result<u64> {
+        if let Some(start_block_height) = self.start_block_height {
+            return Ok(start_block_height);
+        }
+        match self.start_mode {
+            StartMode::FromBlock => {
+                anyhow::bail!("start_block_height is required for `from-block` start mode")
+            }
+            StartMode::FromInterruption => {
+                match crate::db_adapters::checkpoints::get_last_processed_block_height(
+                    pool,
+                    &self.indexer_id,
+                )
+                .await?
+                {
+                    Some(last_processed_block_height) => {
+                        tracing::info!(
+                            target: crate::LOGGING_PREFIX,
+                            "Resuming {} from the checkpoint {}",
+                            self.indexer_id,
+                            last_processed_block_height
+                        );
+                        Ok(last_processed_block_height + 1)
+                    }
+                    None => anyhow::bail!(
+                        "Checkpoint for {} is not found, please provide start_block_height",
+                        self.indexer_id
+                    ),
+                }
+            }
+        }
+    }
+
+    pub fn get_end_block_height(&self, start_block_height: u64) -> anyhow::Result<Option<u64>> {
+        let end_block_height = match (self.end_block_height, self.blocks_count) {
+            (Some(end_block_height), _) => end_block_height,
+            (None, Some(0)) => anyhow::bail!("blocks_count should be positive"),
+            (None, Some(blocks_count)) => start_block_height + blocks_count - 1,
+            (None, None) => return Ok(None),
+        };
+        if end_block_height < start_block_height {
+            anyhow::bail!(
+                "end_block_height {} is less than start_block_height {}",
+                end_block_height,
+                start_block_height
+            );
+        }
+        Ok(Some(end_block_height))
+    }
+
+    // Each block in flight holds the connection of its transaction until it's committed:
+    // `concurrency` stored blocks wait in the buffer and one more is being committed.
+    // One connection is taken by the leases, the margin is left for the queries outside of the transactions
+    pub fn max_db_connections(&self) -> u32 {
+        let required = self.concurrency.get() + 2 + DB_CONNECTIONS_MARGIN;
+        u32::try_from(required).unwrap_or(u32::MAX)
+    }
+
+    pub fn rpc_client(&self) -> near_jsonrpc_client::JsonRpcClient {
+        near_jsonrpc_client::JsonRpcClient::connect(&self.near_archival_rpc_url)
+    }
+
     // returns a Lake Config object where AWS credentials are sourced from .env file first, and then from .aws/credentials if not found.
     // https://docs.aws.amazon.com/sdk-for-rust/latest/dg/credentials.html
-    pub async fn to_lake_config(&self) -> near_lake_framework::LakeConfig {
-        let config_builder = near_lake_framework::LakeConfigBuilder::default();
+    pub async fn to_lake_config(
+        &self,
+        start_block_height: u64,
+    ) -> anyhow::Result<near_lake_framework::LakeConfig> {
+        let mut config_builder = near_lake_framework::LakeConfigBuilder::default();
 
         tracing::info!(target: crate::LOGGING_PREFIX, "CHAIN_ID: {}", self.chain_id);
 
-        match self.chain_id.as_str() {
-            "mainnet" => config_builder.mainnet(),
-            "testnet" => config_builder.testnet(),
-            invalid_chain => panic!(
-                "Invalid CHAIN_ID: `{}`. Try `mainnet` or `testnet`",
-                invalid_chain
+        config_builder = match (self.chain_id.as_str(), &self.lake_s3_bucket_name) {
+            (_, Some(s3_bucket_name)) => {
+                let s3_region_name = self.lake_s3_region_name.as_ref().ok_or_else(|| {
+                    anyhow::anyhow!("LAKE_S3_REGION_NAME is required together with LAKE_S3_BUCKET_NAME")
+                })?;
+                config_builder
+                    .s3_bucket_name(s3_bucket_name)
+                    .s3_region_name(s3_region_name)
+            }
+            ("mainnet", None) => config_builder.mainnet(),
+            ("testnet", None) => config_builder.testnet(),
+            (custom_chain, None) => anyhow::bail!(
+                "NEAR Lake bucket is unknown for CHAIN_ID: `{}`. Provide LAKE_S3_BUCKET_NAME or try `mainnet` or `testnet`",
+                custom_chain
             ),
+        };
+        if let Some(s3_endpoint) = &self.lake_s3_endpoint {
+            let aws_config = aws_config::from_env().load().await;
+            let mut s3_config = aws_sdk_s3::config::Builder::from(&aws_config).endpoint_resolver(
+                aws_sdk_s3::Endpoint::immutable(
+                    s3_endpoint
+                        .parse::<http::Uri>()
+                        .map_err(|err| anyhow::anyhow!("Invalid LAKE_S3_ENDPOINT: {}", err))?,
+                ),
+            );
+            if let Some(s3_region_name) = &self.lake_s3_region_name {
+                s3_config = s3_config.region(aws_sdk_s3::Region::new(s3_region_name.clone()));
+            }
+            config_builder = config_builder.s3_config(s3_config.build());
         }
-        .start_block_height(self.start_block_height)
-        .build()
-        .expect("Failed to build LakeConfig")
+
+        config_builder
+            .start_block_height(start_block_height)
+            .build()
+            .map_err(|err| anyhow::anyhow!("Failed to build LakeConfig: {}", err))
     }
 }
 
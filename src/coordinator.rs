@@ -0,0 +1,136 @@
+use crate::configs::Opts;
+use crate::db_adapters::leases;
+use crate::{metrics, shutdown, Checkpoint, LOGGING_PREFIX};
+
+// How often the idle worker checks if some of the ranges were abandoned
+const IDLE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+// Coordinator mode: all the workers split the range [start_block_height, end_block_height]
+// into the leases stored in the DB, index them one by one and finish when all the ranges are completed.
+// If the worker crashes, its lease expires and the range is continued by another worker
+pub(crate) async fn run(
+    pool: sqlx::Pool<sqlx::Postgres>,
+    opts: Opts,
+    shutdown: tokio::sync::watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let start_block_height = opts
+        .start_block_height
+        .ok_or_else(|| anyhow::anyhow!("start_block_height is required for coordinator mode"))?;
+    let end_block_height = opts
+        .get_end_block_height(start_block_height)?
+        .ok_or_else(|| {
+            anyhow::anyhow!("end_block_height or blocks_count is required for coordinator mode")
+        })?;
+    if opts.range_size == 0 {
+        anyhow::bail!("range_size should be positive");
+    }
+    let lease_timeout = std::time::Duration::from_secs(opts.lease_timeout_secs);
+    let worker_id = format!(
+        "{}@{}:{}",
+        opts.indexer_id,
+        std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string()),
+        std::process::id()
+    );
+
+    leases::create_leases(&pool, start_block_height, end_block_height, opts.range_size).await?;
+    tracing::warn!(
+        target: LOGGING_PREFIX,
+        "ft_balances is not updated in coordinator mode, run rebuild-ft-balances when all the ranges are completed"
+    );
+    tracing::info!(
+        target: LOGGING_PREFIX,
+        "Worker {} joined indexing of blocks {}..={}",
+        worker_id,
+        start_block_height,
+        end_block_height
+    );
+
+    while !shutdown::is_shutdown_requested(&shutdown) {
+        let lease = leases::claim_lease(
+            &pool,
+            &worker_id,
+            start_block_height,
+            end_block_height,
+            lease_timeout,
+        )
+        .await?;
+
+        match lease {
+            Some(lease) => {
+                let range_start_from = lease
+                    .last_processed_block_height
+                    .map_or(lease.range_start, |height| height + 1);
+                tracing::info!(
+                    target: LOGGING_PREFIX,
+                    "Worker {} claimed blocks {}..={}, starting from {}",
+                    worker_id,
+                    lease.range_start,
+                    lease.range_end,
+                    range_start_from
+                );
+
+                if range_start_from <= lease.range_end {
+                    let (streamer_handle, stream) =
+                        crate::streamer(&opts, range_start_from, Some(lease.range_end)).await?;
+                    let checkpoint = Checkpoint::Lease {
+                        worker_id: worker_id.clone(),
+                        range_start: lease.range_start,
+                        lease_timeout,
+                    };
+                    crate::index_blocks(
+                        &pool,
+                        streamer_handle,
+                        stream,
+                        Some(lease.range_end),
+                        &checkpoint,
+                        &opts,
+                        shutdown.clone(),
+                    )
+                    .await?;
+                    if shutdown::is_shutdown_requested(&shutdown) {
+                        // The range is not completed, another worker will continue it after the lease expires
+                        return Ok(());
+                    }
+                }
+                leases::complete_lease(&pool, &worker_id, lease.range_start).await?;
+                report_progress(&pool, start_block_height, end_block_height).await?;
+            }
+            None => {
+                let progress = report_progress(&pool, start_block_height, end_block_height).await?;
+                if progress.completed_ranges == progress.total_ranges {
+                    tracing::warn!(
+                        target: LOGGING_PREFIX,
+                        "All the ranges are completed, stop the indexers and run rebuild-ft-balances"
+                    );
+                    return Ok(());
+                }
+                // The rest of the ranges are being indexed by other workers.
+                // We wait for them in case some of them crash
+                tokio::select! {
+                    _ = tokio::time::sleep(IDLE_INTERVAL) => {},
+                    _ = shutdown::wait_for_shutdown(shutdown.clone()) => {},
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn report_progress(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    start_block_height: u64,
+    end_block_height: u64,
+) -> anyhow::Result<leases::LeasesProgress> {
+    let progress = leases::get_progress(pool, start_block_height, end_block_height).await?;
+    metrics::COMPLETED_RANGES.set(progress.completed_ranges);
+    metrics::TOTAL_RANGES.set(progress.total_ranges);
+    tracing::info!(
+        target: LOGGING_PREFIX,
+        "Progress of blocks {}..={}: {}/{} ranges completed",
+        start_block_height,
+        end_block_height,
+        progress.completed_ranges,
+        progress.total_ranges
+    );
+    Ok(progress)
+}
@@ -0,0 +1,45 @@
+use bigdecimal::{BigDecimal, ToPrimitive};
+
+pub(crate) async fn get_last_processed_block_height(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    indexer_id: &str,
+) -> anyhow::Result<Option<u64>> {
+    let height: Option<(BigDecimal,)> = sqlx::query_as(
+        "SELECT last_processed_block_height FROM indexer_checkpoints WHERE indexer_id = $1",
+    )
+    .bind(indexer_id)
+    .fetch_optional(pool)
+    .await?;
+
+    height
+        .map(|(height,)| {
+            height.to_u64().ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Checkpoint {} for {} is not a valid block height",
+                    height,
+                    indexer_id
+                )
+            })
+        })
+        .transpose()
+}
+
+// The checkpoint is overwritten even if it moves back,
+// it happens when we restart the indexer from the explicit start_block_height
+pub(crate) async fn store_checkpoint(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    indexer_id: &str,
+    block_height: u64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO indexer_checkpoints VALUES ($1, $2, now()) \
+         ON CONFLICT (indexer_id) DO UPDATE SET \
+         last_processed_block_height = excluded.last_processed_block_height, \
+         updated_at = excluded.updated_at",
+    )
+    .bind(indexer_id)
+    .bind(BigDecimal::from(block_height))
+    .execute(tx)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, HashSet};
+
+use bigdecimal::BigDecimal;
+use cached::{Cached, SizedCache};
+use futures::{StreamExt, TryStreamExt};
+use near_jsonrpc_client::JsonRpcClient;
+use near_lake_framework::near_indexer_primitives;
+
+use crate::db_adapters::coin::reconciliation;
+use crate::models::coin_events::CoinEvent;
+use crate::AccountWithContract;
+
+// Some contracts have millions of accounts, we keep only the recently active ones
+const BALANCES_CACHE_SIZE: usize = 100_000;
+const RPC_CONCURRENCY: usize = 10;
+
+/// Balances after the last stored block, maintained from the deltas of the events.
+/// The accounts which are not cached (first seen or evicted) are seeded from RPC.
+/// `None` is cached for the contracts which can't answer `ft_balance_of`,
+/// the contracts which are not deployed yet start from zero balances
+pub(crate) struct Balances {
+    cache: SizedCache<AccountWithContract, Option<BigDecimal>>,
+}
+
+impl Balances {
+    pub fn new() -> Self {
+        Self {
+            cache: SizedCache::with_size(BALANCES_CACHE_SIZE),
+        }
+    }
+
+    // Should be called only after the block is committed, the failed block is stored again from the same balances
+    pub fn update(&mut self, block_balances: BlockBalances) {
+        for (account_with_contract, balance) in block_balances.0 {
+            self.cache.cache_set(account_with_contract, balance);
+        }
+    }
+}
+
+/// The balances at the end of the block, for all the accounts affected by the block
+#[derive(Default)]
+pub(crate) struct BlockBalances(HashMap<AccountWithContract, Option<BigDecimal>>);
+
+// Should be called for the blocks in their order, otherwise the cached balances are wrong
+pub(crate) async fn fill_absolute_amounts(
+    rpc_client: &JsonRpcClient,
+    balances: &mut Balances,
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    events: &mut [CoinEvent],
+) -> anyhow::Result<BlockBalances> {
+    // event_index follows the order of the execution inside each contract
+    events.sort_by(|a, b| a.event_index.cmp(&b.event_index));
+    let mut accounts = Vec::with_capacity(events.len());
+    for event in events.iter() {
+        accounts.push(AccountWithContract {
+            account_id: event.affected_account_id.parse()?,
+            contract_account_id: event.contract_account_id.parse()?,
+        });
+    }
+
+    let mut block_balances: HashMap<AccountWithContract, Option<BigDecimal>> = HashMap::new();
+    let mut missing_accounts = vec![];
+    for account_with_contract in accounts.iter().collect::<HashSet<_>>() {
+        match balances.cache.cache_get(account_with_contract) {
+            Some(balance) => {
+                block_balances.insert(account_with_contract.clone(), balance.clone());
+            }
+            None => missing_accounts.push(account_with_contract.clone()),
+        }
+    }
+
+    // The balances at the end of the previous block
+    let seeds: Vec<(AccountWithContract, Option<BigDecimal>)> =
+        futures::stream::iter(missing_accounts.into_iter().map(
+            |account_with_contract| async move {
+                let balance = get_ft_balance_or_retry(
+                    rpc_client,
+                    &account_with_contract,
+                    &block_header.prev_hash,
+                )
+                .await?;
+                anyhow::Ok((account_with_contract, balance))
+            },
+        ))
+        .buffer_unordered(RPC_CONCURRENCY)
+        .try_collect()
+        .await?;
+    block_balances.extend(seeds);
+
+    for (event, account_with_contract) in events.iter_mut().zip(&accounts) {
+        if let Some(Some(balance)) = block_balances.get_mut(account_with_contract) {
+            // The failed receipts do not change the balances
+            if event.status == "SUCCESS" {
+                *balance += &event.delta_amount;
+            }
+            event.absolute_amount = Some(balance.clone());
+        }
+    }
+
+    Ok(BlockBalances(block_balances))
+}
+
+async fn get_ft_balance_or_retry(
+    rpc_client: &JsonRpcClient,
+    account_with_contract: &AccountWithContract,
+    block_hash: &near_indexer_primitives::CryptoHash,
+) -> anyhow::Result<Option<BigDecimal>> {
+    let mut interval = crate::INTERVAL;
+    let mut retry_attempt = 0usize;
+
+    loop {
+        retry_attempt += 1;
+        match reconciliation::get_ft_balance(rpc_client, account_with_contract, block_hash).await {
+            Ok(balance) => return Ok(balance),
+            Err(err) if retry_attempt < crate::db_adapters::RETRY_COUNT => {
+                tracing::warn!(
+                    target: crate::LOGGING_PREFIX,
+                    "{:#}\n Retrying in {} milliseconds...",
+                    err,
+                    interval.as_millis(),
+                );
+                tokio::time::sleep(interval).await;
+                if interval < crate::MAX_DELAY_TIME {
+                    interval *= 2;
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_adapters::test_utils::{read_fixture, start_rpc};
+
+    // Answers all the queries as RPC does for the contract which is not deployed yet
+    fn respond_without_contract(_request: &serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "error": {
+                "name": "HANDLER_ERROR",
+                "cause": { "name": "UNKNOWN_ACCOUNT", "info": {} },
+                "code": -32000,
+                "message": "Server error",
+                "data": "account token.near does not exist while viewing",
+            },
+        })
+    }
+
+    fn coin_event(event_index: u64, account_id: &str, delta_amount: i64) -> CoinEvent {
+        CoinEvent {
+            event_index: BigDecimal::from(event_index),
+            standard: "nep141".to_string(),
+            receipt_id: "receipt".to_string(),
+            block_height: BigDecimal::from(0),
+            block_timestamp: BigDecimal::from(0),
+            contract_account_id: "token.near".to_string(),
+            affected_account_id: account_id.to_string(),
+            involved_account_id: None,
+            delta_amount: BigDecimal::from(delta_amount),
+            cause: "TRANSFER".to_string(),
+            status: "SUCCESS".to_string(),
+            event_memo: None,
+            absolute_amount: None,
+            event_version: None,
+            unsupported_event_version: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn starts_from_zero_when_contract_is_deployed_in_the_block() {
+        let rpc_client = JsonRpcClient::connect(start_rpc(respond_without_contract));
+        let block_header = read_fixture("nep141_events").block_header;
+        let mut balances = Balances::new();
+
+        // The contract is deployed, mints the coins and transfers some of them in one block
+        let mut events = vec![
+            coin_event(2, "bob.near", 30),
+            coin_event(0, "alice.near", 100),
+            coin_event(1, "alice.near", -30),
+        ];
+        let block_balances =
+            fill_absolute_amounts(&rpc_client, &mut balances, &block_header, &mut events)
+                .await
+                .unwrap();
+        balances.update(block_balances);
+        let absolute_amounts: Vec<_> = events
+            .iter()
+            .map(|event| {
+                (
+                    event.affected_account_id.as_str(),
+                    event.absolute_amount.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            absolute_amounts,
+            vec![
+                ("alice.near", Some(BigDecimal::from(100))),
+                ("alice.near", Some(BigDecimal::from(70))),
+                ("bob.near", Some(BigDecimal::from(30))),
+            ]
+        );
+
+        // The next block continues from the cached balances, RPC would give zero again
+        let mut events = vec![coin_event(0, "alice.near", -20)];
+        fill_absolute_amounts(&rpc_client, &mut balances, &block_header, &mut events)
+            .await
+            .unwrap();
+        assert_eq!(events[0].absolute_amount, Some(BigDecimal::from(50)));
+    }
+}
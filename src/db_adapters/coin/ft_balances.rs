@@ -0,0 +1,58 @@
+use std::collections::BTreeMap;
+
+use bigdecimal::BigDecimal;
+
+use crate::configs::InsertMethod;
+use crate::models;
+use crate::models::coin_events::CoinEvent;
+use crate::models::ft_balances::FtBalance;
+
+// The balances are shared between the blocks, so this is called in the order of the blocks.
+// Re-running the block does not change the balances, the rows already include its last event.
+// The earlier block stored after the later one is skipped the same way, so it's not called in coordinator mode.
+// The rows are sorted by the primary key: the concurrent writers always lock them in the same order
+pub(crate) async fn store_ft_balances(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    events: &[CoinEvent],
+    insert_method: InsertMethod,
+) -> anyhow::Result<()> {
+    let mut balances: BTreeMap<(&str, &str), FtBalance> = BTreeMap::new();
+    // The failed receipts do not change the balances
+    for event in events.iter().filter(|event| event.status == "SUCCESS") {
+        let balance = balances
+            .entry((&event.contract_account_id, &event.affected_account_id))
+            .or_insert_with(|| FtBalance {
+                contract_account_id: event.contract_account_id.clone(),
+                account_id: event.affected_account_id.clone(),
+                balance: BigDecimal::default(),
+                last_event_index: event.event_index.clone(),
+            });
+        balance.balance += &event.delta_amount;
+        if balance.last_event_index < event.event_index {
+            balance.last_event_index = event.event_index.clone();
+        }
+    }
+
+    let balances: Vec<FtBalance> = balances.into_values().collect();
+    models::insert(tx, &balances, insert_method).await
+}
+
+/// Recomputes all the balances from the stored `coin_events`.
+/// The indexers writing to the same DB should be stopped, otherwise their blocks could be lost
+pub(crate) async fn rebuild_ft_balances(pool: &sqlx::Pool<sqlx::Postgres>) -> anyhow::Result<u64> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM ft_balances")
+        .execute(&mut tx)
+        .await?;
+    let rows_count = sqlx::query(
+        "INSERT INTO ft_balances \
+         SELECT contract_account_id, affected_account_id, sum(delta_amount), max(event_index) \
+         FROM coin_events WHERE status = 'SUCCESS' \
+         GROUP BY contract_account_id, affected_account_id",
+    )
+    .execute(&mut tx)
+    .await?
+    .rows_affected();
+    tx.commit().await?;
+    Ok(rows_count)
+}
@@ -0,0 +1,134 @@
+use crate::db_adapters::legacy::{deserialize_regex, ChainAccounts};
+use serde::Deserialize;
+
+/// The description of all the legacy contracts, see DOC.md for the format
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LegacyConfig {
+    pub contracts: Vec<LegacyContract>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LegacyContract {
+    /// Used in the logs
+    pub name: String,
+    /// Goes to `event_index` of the events, see `LegacyHandler::event_type_index`
+    pub event_type_index: u8,
+    pub accounts: ChainAccounts,
+    /// The methods which do not change the balances
+    #[serde(default)]
+    pub ignored_methods: Vec<String>,
+    pub rules: Vec<MethodRule>,
+}
+
+// `deny_unknown_fields` does not work together with `flatten`
+#[derive(Deserialize, Debug)]
+pub(crate) struct MethodRule {
+    pub methods: Vec<String>,
+    /// The arguments are JSON by default
+    #[serde(default)]
+    pub borsh_args: Option<Vec<BorshField>>,
+    #[serde(flatten)]
+    pub action: MethodAction,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub(crate) enum MethodAction {
+    /// MINT to the account from the arguments
+    MintFromArgs {
+        account_id_field: String,
+        amount_field: String,
+    },
+    /// MINT of the attached deposit to the predecessor
+    MintFromDeposit,
+    /// MINT for each log with `amount` and `account_id` groups
+    MintFromLogs {
+        #[serde(deserialize_with = "deserialize_regex")]
+        log_pattern: regex::Regex,
+    },
+    /// TRANSFER from the predecessor to the account from the arguments
+    Transfer {
+        receiver_id_field: String,
+        amount_field: String,
+        memo_field: Option<String>,
+    },
+    /// `ft_resolve_transfer` of the standard implementation.
+    /// The amount from the arguments minus the returned value is sent back to the sender
+    /// if there's the refund log, or burnt if there's the log about the deleted sender
+    Refund {
+        sender_id_field: String,
+        receiver_id_field: String,
+        amount_field: String,
+        memo_field: Option<String>,
+        refund_log_prefix: String,
+        deleted_sender_log: String,
+    },
+    /// TRANSFER for each log with `amount`, `from_account_id` and `to_account_id` groups
+    RefundFromLogs {
+        #[serde(deserialize_with = "deserialize_regex")]
+        log_pattern: regex::Regex,
+    },
+    /// BURN from the predecessor
+    Burn { amount_field: String },
+}
+
+/// The fields of borsh-serialized arguments in their order
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct BorshField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: BorshType,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum BorshType {
+    /// Ethereum address, 20 bytes
+    Address,
+    U128,
+}
+
+impl BorshType {
+    pub fn size(&self) -> usize {
+        match self {
+            BorshType::Address => 20,
+            BorshType::U128 => 16,
+        }
+    }
+}
+
+pub(crate) fn parse_config(config: &str) -> anyhow::Result<LegacyConfig> {
+    let config: LegacyConfig = toml::from_str(config)?;
+    for contract in &config.contracts {
+        for rule in &contract.rules {
+            let (log_pattern, groups) = match &rule.action {
+                MethodAction::MintFromLogs { log_pattern } => {
+                    (log_pattern, &["amount", "account_id"][..])
+                }
+                MethodAction::RefundFromLogs { log_pattern } => (
+                    log_pattern,
+                    &["amount", "from_account_id", "to_account_id"][..],
+                ),
+                _ => continue,
+            };
+            for group in groups {
+                if !log_pattern
+                    .capture_names()
+                    .flatten()
+                    .any(|name| name == *group)
+                {
+                    anyhow::bail!(
+                        "Log pattern of {} in legacy contract {} has no `{}` group",
+                        rule.methods.join(", "),
+                        contract.name,
+                        group
+                    );
+                }
+            }
+        }
+    }
+    Ok(config)
+}
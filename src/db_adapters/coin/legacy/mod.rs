@@ -1,59 +1,799 @@
+use crate::db_adapters;
+use crate::db_adapters::legacy::{
+    get_field, get_memo, ignore_failed_receipt, LegacyHandler, LegacyRegistry,
+};
+use crate::db_adapters::rejected_events::build_rejected_action;
+use crate::db_adapters::{coin, numeric_types, Event};
 use crate::models::coin_events::CoinEvent;
-use futures::try_join;
+use crate::models::rejected_events::RejectedEvent;
+use anyhow::Context;
+use bigdecimal::BigDecimal;
+use config::{BorshField, BorshType, LegacyContract, MethodAction, MethodRule};
 use near_lake_framework::near_indexer_primitives;
+use near_primitives::types::AccountId;
+use near_primitives::views::{ActionView, ExecutionStatusView, ReceiptEnumView};
+use once_cell::sync::OnceCell;
+use std::ops::{Mul, Sub};
+use std::path::Path;
+use std::str::FromStr;
 
-mod aurora;
-mod rainbow_bridge;
-mod skyward;
-mod tkn_near;
-mod wentokensir;
-mod wrap_near;
+mod config;
+
+static LEGACY_HANDLERS: OnceCell<LegacyRegistry<CoinEvent>> = OnceCell::new();
+
+/// Reads the legacy FT contracts from the given config, `contracts.toml` embedded to the binary is used otherwise.
+/// Should be called on startup, before any block is handled
+pub(crate) fn init_legacy_handlers(config_path: Option<&Path>) -> anyhow::Result<()> {
+    let registry = build_registry(config_path)?;
+    LEGACY_HANDLERS
+        .set(registry)
+        .map_err(|_| anyhow::anyhow!("Legacy FT handlers are already initialized"))
+}
+
+fn legacy_handlers() -> anyhow::Result<&'static LegacyRegistry<CoinEvent>> {
+    LEGACY_HANDLERS.get_or_try_init(|| build_registry(None))
+}
+
+// The contracts from the config are handled by one engine,
+// the handlers implemented in code should be registered here as well
+fn build_registry(config_path: Option<&Path>) -> anyhow::Result<LegacyRegistry<CoinEvent>> {
+    let config = match config_path {
+        Some(path) => {
+            let config = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            config::parse_config(&config)
+                .with_context(|| format!("Invalid legacy FT contracts config {}", path.display()))?
+        }
+        None => config::parse_config(include_str!("contracts.toml"))?,
+    };
+    let mut registry = LegacyRegistry::default();
+    for contract in config.contracts {
+        registry.register(Box::new(contract))?;
+    }
+    Ok(registry)
+}
 
 pub(crate) async fn collect_legacy(
     shard_id: &near_indexer_primitives::types::ShardId,
     receipt_execution_outcomes: &[near_indexer_primitives::IndexerExecutionOutcomeWithReceipt],
     block_header: &near_indexer_primitives::views::BlockHeaderView,
     chain_id: &str,
-) -> anyhow::Result<Vec<CoinEvent>> {
-    // We don't need to store legacy events for testnet
-    if chain_id == "testnet" {
-        return Ok(vec![]);
+) -> anyhow::Result<(Vec<CoinEvent>, Vec<RejectedEvent>)> {
+    let mut events: Vec<CoinEvent> = vec![];
+    let mut rejected_events: Vec<RejectedEvent> = vec![];
+    for handler in legacy_handlers()?.handlers() {
+        let (handler_events, handler_rejected_events) = collect_handler(
+            handler,
+            shard_id,
+            receipt_execution_outcomes,
+            block_header,
+            chain_id,
+        )
+        .await?;
+        events.extend(handler_events);
+        rejected_events.extend(handler_rejected_events);
     }
+    Ok((events, rejected_events))
+}
+
+async fn collect_handler(
+    handler: &dyn LegacyHandler<Row = CoinEvent>,
+    shard_id: &near_indexer_primitives::types::ShardId,
+    receipt_execution_outcomes: &[near_indexer_primitives::IndexerExecutionOutcomeWithReceipt],
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    chain_id: &str,
+) -> anyhow::Result<(Vec<CoinEvent>, Vec<RejectedEvent>)> {
     let mut events: Vec<CoinEvent> = vec![];
+    let mut rejected_events: Vec<RejectedEvent> = vec![];
 
-    let aurora_future = aurora::collect_aurora(shard_id, receipt_execution_outcomes, block_header);
-    let rainbow_bridge_future =
-        rainbow_bridge::collect_rainbow_bridge(shard_id, receipt_execution_outcomes, block_header);
-    let skyward_future =
-        skyward::collect_skyward(shard_id, receipt_execution_outcomes, block_header);
-    let tkn_near_future =
-        tkn_near::collect_tkn_near(shard_id, receipt_execution_outcomes, block_header);
-    let wentokensir_future =
-        wentokensir::collect_wentokensir(shard_id, receipt_execution_outcomes, block_header);
-    let wrap_near_future =
-        wrap_near::collect_wrap_near(shard_id, receipt_execution_outcomes, block_header);
-
-    let (
-        aurora_events,
-        rainbow_bridge_events,
-        skyward_events,
-        tkn_near_events,
-        wentokensir_events,
-        wrap_near_events,
-    ) = try_join!(
-        aurora_future,
-        rainbow_bridge_future,
-        skyward_future,
-        tkn_near_future,
-        wentokensir_future,
-        wrap_near_future
+    for outcome in receipt_execution_outcomes {
+        if !handler.matches_receiver(chain_id, &outcome.receipt.receiver_id)
+            || !db_adapters::events::extract_events(outcome).is_empty()
+        {
+            continue;
+        }
+        if let ReceiptEnumView::Action { actions, .. } = &outcome.receipt.receipt {
+            for (action_index, action) in actions.iter().enumerate() {
+                match handler.check_action_supported(action) {
+                    Ok(()) => events.extend(
+                        handler
+                            .process_action(block_header, action, outcome)
+                            .await?,
+                    ),
+                    // The failed action did not change anything, so nothing is lost
+                    Err(_)
+                        if db_adapters::get_status(&outcome.execution_outcome.outcome.status)
+                            != "SUCCESS" => {}
+                    Err(err) => rejected_events.push(build_rejected_action(
+                        outcome,
+                        block_header,
+                        action_index,
+                        action,
+                        &err,
+                    )?),
+                }
+            }
+        }
+    }
+    coin::filter_zeros_and_enumerate_events(
+        &mut events,
+        shard_id,
+        block_header.timestamp,
+        &Event::FtLegacy(handler.event_type_index()),
     )?;
 
-    events.extend(aurora_events);
-    events.extend(rainbow_bridge_events);
-    events.extend(skyward_events);
-    events.extend(tkn_near_events);
-    events.extend(wentokensir_events);
-    events.extend(wrap_near_events);
-    Ok(events)
+    Ok((events, rejected_events))
+}
+
+#[async_trait::async_trait]
+impl LegacyHandler for LegacyContract {
+    type Row = CoinEvent;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn event_type_index(&self) -> u8 {
+        self.event_type_index
+    }
+
+    fn matches_receiver(&self, chain_id: &str, account_id: &AccountId) -> bool {
+        self.accounts.is_match(chain_id, account_id)
+    }
+
+    async fn process_action(
+        &self,
+        block_header: &near_indexer_primitives::views::BlockHeaderView,
+        action: &ActionView,
+        outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    ) -> anyhow::Result<Vec<CoinEvent>> {
+        process_function(self, block_header, action, outcome).await
+    }
+}
+
+async fn process_function(
+    contract: &LegacyContract,
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    action: &ActionView,
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+) -> anyhow::Result<Vec<CoinEvent>> {
+    let (method_name, args, deposit) = match action {
+        ActionView::FunctionCall {
+            method_name,
+            args,
+            deposit,
+            ..
+        } => (method_name, args, deposit),
+        _ => return Ok(vec![]),
+    };
+
+    let decoded_args = base64::decode(args)?;
+
+    if contract.ignored_methods.contains(method_name) {
+        return Ok(vec![]);
+    }
+    let rule = match contract
+        .rules
+        .iter()
+        .find(|rule| rule.methods.contains(method_name))
+    {
+        Some(rule) => rule,
+        None => {
+            tracing::error!(
+                target: crate::LOGGING_PREFIX,
+                "{} {} new method found: {}, receipt {}",
+                contract.name,
+                block_header.height,
+                method_name,
+                outcome.receipt.receipt_id
+            );
+            return Ok(vec![]);
+        }
+    };
+    let event_builder = EventBuilder {
+        event: Event::FtLegacy(contract.event_type_index),
+        block_header,
+        outcome,
+    };
+
+    match &rule.action {
+        MethodAction::MintFromArgs {
+            account_id_field,
+            amount_field,
+        } => {
+            let (account_id, amount) = match parse_args(outcome, rule, &decoded_args, |args| {
+                Ok((
+                    get_field::<AccountId>(args, account_id_field)?,
+                    get_amount(args, amount_field)?,
+                ))
+            })? {
+                Some(x) => x,
+                None => return Ok(vec![]),
+            };
+            Ok(vec![
+                event_builder
+                    .build(account_id, None, amount, "MINT", None)
+                    .await?,
+            ])
+        }
+
+        MethodAction::MintFromDeposit => {
+            let delta = BigDecimal::from_str(&deposit.to_string())?;
+            let affected_id = outcome.receipt.predecessor_id.clone();
+            Ok(vec![
+                event_builder
+                    .build(affected_id, None, delta, "MINT", None)
+                    .await?,
+            ])
+        }
+
+        MethodAction::MintFromLogs { log_pattern } => {
+            let mut events = vec![];
+            for log in &outcome.execution_outcome.outcome.logs {
+                if let Some(cap) = log_pattern.captures(log) {
+                    let delta = BigDecimal::from_str(&cap["amount"])?;
+                    let affected_id = AccountId::from_str(&cap["account_id"])?;
+                    events.push(
+                        event_builder
+                            .build(affected_id, None, delta, "MINT", None)
+                            .await?,
+                    );
+                }
+            }
+            Ok(events)
+        }
+
+        // TRANSFER produces 2 events
+        // 1. affected_account_id is sender, delta is negative, absolute_amount decreased
+        // 2. affected_account_id is receiver, delta is positive, absolute_amount increased
+        MethodAction::Transfer {
+            receiver_id_field,
+            amount_field,
+            memo_field,
+        } => {
+            let (receiver_id, delta, memo) =
+                match parse_args(outcome, rule, &decoded_args, |args| {
+                    Ok((
+                        get_field::<AccountId>(args, receiver_id_field)?,
+                        get_amount(args, amount_field)?,
+                        get_memo(args, memo_field)?,
+                    ))
+                })? {
+                    Some(x) => x,
+                    None => return Ok(vec![]),
+                };
+            event_builder
+                .build_transfer(
+                    outcome.receipt.predecessor_id.clone(),
+                    receiver_id,
+                    delta,
+                    memo,
+                )
+                .await
+        }
+
+        // If TRANSFER failed, it could be revoked. The procedure is the same as for TRANSFER
+        MethodAction::Refund {
+            sender_id_field,
+            receiver_id_field,
+            amount_field,
+            memo_field,
+            refund_log_prefix,
+            deleted_sender_log,
+        } => {
+            if outcome.execution_outcome.outcome.logs.is_empty() {
+                // ft_transfer_call was successful, there's nothing to return back
+                return Ok(vec![]);
+            }
+            let (sender_id, receiver_id, mut delta, memo) =
+                match parse_args(outcome, rule, &decoded_args, |args| {
+                    Ok((
+                        get_field::<AccountId>(args, sender_id_field)?,
+                        get_field::<AccountId>(args, receiver_id_field)?,
+                        get_amount(args, amount_field)?,
+                        get_memo(args, memo_field)?,
+                    ))
+                })? {
+                    Some(x) => x,
+                    None => return Ok(vec![]),
+                };
+            // The contract may return only the part of the coins.
+            // We should parse it from the output and subtract from the value from args
+            if let ExecutionStatusView::SuccessValue(transferred_amount_decoded) =
+                &outcome.execution_outcome.outcome.status
+            {
+                let transferred_amount =
+                    serde_json::from_slice::<String>(&base64::decode(transferred_amount_decoded)?)?;
+                delta = delta.sub(BigDecimal::from_str(&transferred_amount)?);
+            }
+
+            for log in &outcome.execution_outcome.outcome.logs {
+                if log == deleted_sender_log {
+                    // I never met this case so it's better to re-check it manually when we find it
+                    tracing::error!(
+                        target: crate::LOGGING_PREFIX,
+                        "The account of the sender was deleted {}",
+                        block_header.height
+                    );
+
+                    // we should revert ft_transfer_call, but there's no receiver_id. We should burn tokens
+                    return Ok(vec![
+                        event_builder
+                            .build(
+                                receiver_id,
+                                None,
+                                delta.mul(BigDecimal::from(-1)),
+                                "BURN",
+                                memo,
+                            )
+                            .await?,
+                    ]);
+                }
+                if log.starts_with(refund_log_prefix.as_str()) {
+                    // we should revert ft_transfer_call
+                    return event_builder
+                        .build_transfer(receiver_id, sender_id, delta, memo)
+                        .await;
+                }
+            }
+            Ok(vec![])
+        }
+
+        MethodAction::RefundFromLogs { log_pattern } => {
+            let mut events = vec![];
+            for log in &outcome.execution_outcome.outcome.logs {
+                if let Some(cap) = log_pattern.captures(log) {
+                    let delta = BigDecimal::from_str(&cap["amount"])?;
+                    let from_account_id = AccountId::from_str(&cap["from_account_id"])?;
+                    let to_account_id = AccountId::from_str(&cap["to_account_id"])?;
+                    events.extend(
+                        event_builder
+                            .build_transfer(from_account_id, to_account_id, delta, None)
+                            .await?,
+                    );
+                }
+            }
+            Ok(events)
+        }
+
+        // BURN produces 1 event, where involved_account_id is NULL
+        MethodAction::Burn { amount_field } => {
+            let delta = match parse_args(outcome, rule, &decoded_args, |args| {
+                get_amount(args, amount_field)
+            })? {
+                Some(x) => x,
+                None => return Ok(vec![]),
+            };
+            let affected_id = outcome.receipt.predecessor_id.clone();
+            Ok(vec![
+                event_builder
+                    .build(
+                        affected_id,
+                        None,
+                        delta.mul(BigDecimal::from(-1)),
+                        "BURN",
+                        None,
+                    )
+                    .await?,
+            ])
+        }
+    }
+}
+
+struct EventBuilder<'a> {
+    event: Event,
+    block_header: &'a near_indexer_primitives::views::BlockHeaderView,
+    outcome: &'a near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+}
+
+impl EventBuilder<'_> {
+    async fn build(
+        &self,
+        affected_id: AccountId,
+        involved_id: Option<AccountId>,
+        delta: BigDecimal,
+        cause: &str,
+        memo: Option<String>,
+    ) -> anyhow::Result<CoinEvent> {
+        let base = db_adapters::get_base(self.event, self.outcome, self.block_header)?;
+        let custom = coin::FtEvent {
+            affected_id,
+            involved_id,
+            delta,
+            cause: cause.to_string(),
+            memo,
+        };
+        coin::build_event(base, custom).await
+    }
+
+    async fn build_transfer(
+        &self,
+        from_id: AccountId,
+        to_id: AccountId,
+        delta: BigDecimal,
+        memo: Option<String>,
+    ) -> anyhow::Result<Vec<CoinEvent>> {
+        let negative_delta = delta.clone().mul(BigDecimal::from(-1));
+        Ok(vec![
+            self.build(
+                from_id.clone(),
+                Some(to_id.clone()),
+                negative_delta,
+                "TRANSFER",
+                memo.clone(),
+            )
+            .await?,
+            self.build(to_id, Some(from_id), delta, "TRANSFER", memo)
+                .await?,
+        ])
+    }
+}
+
+// `None` means the arguments of the failed receipt can't be parsed, it's ignored
+fn parse_args<T>(
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    rule: &MethodRule,
+    decoded_args: &[u8],
+    parse: impl FnOnce(&serde_json::Value) -> anyhow::Result<T>,
+) -> anyhow::Result<Option<T>> {
+    let args = match &rule.borsh_args {
+        Some(fields) => decode_borsh_args(fields, decoded_args),
+        None => serde_json::from_slice(decoded_args).map_err(anyhow::Error::from),
+    };
+    ignore_failed_receipt(outcome, args.and_then(|args| parse(&args)))
+}
+
+// The numbers are converted to strings, the same as they are in JSON arguments
+fn decode_borsh_args(fields: &[BorshField], args: &[u8]) -> anyhow::Result<serde_json::Value> {
+    let expected_len: usize = fields.iter().map(|field| field.field_type.size()).sum();
+    if args.len() != expected_len {
+        anyhow::bail!(
+            "Unexpected length of borsh arguments: {}, expected {}",
+            args.len(),
+            expected_len
+        );
+    }
+    let mut decoded = serde_json::Map::new();
+    let mut rest = args;
+    for field in fields {
+        let (value, tail) = rest.split_at(field.field_type.size());
+        let value = match field.field_type {
+            BorshType::Address => hex::encode(value),
+            BorshType::U128 => u128::from_le_bytes(value.try_into()?).to_string(),
+        };
+        decoded.insert(field.name.clone(), serde_json::Value::String(value));
+        rest = tail;
+    }
+    Ok(serde_json::Value::Object(decoded))
+}
+
+fn get_amount(args: &serde_json::Value, field: &str) -> anyhow::Result<BigDecimal> {
+    let amount = get_field::<numeric_types::U128>(args, field)?;
+    Ok(BigDecimal::from_str(&amount.0.to_string())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_adapters::test_utils::collect_fixture;
+    use futures::FutureExt;
+
+    #[test]
+    fn registers_legacy_contracts() {
+        let registry = build_registry(None).unwrap();
+        let names: Vec<_> = registry.handlers().map(|handler| handler.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "AURORA",
+                "RAINBOW",
+                "SKYWARD",
+                "TKN",
+                "WENTOKENSIR",
+                "WRAP NEAR"
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_legacy_contracts_from_file() {
+        let config_dir = tempfile::tempdir().unwrap();
+        let config_path = config_dir.path().join("contracts.toml");
+        std::fs::write(
+            &config_path,
+            r#"
+            [[contracts]]
+            name = "WRAP LOCALNET"
+            event_type_index = 8
+            accounts = { localnet = { exact = "wrap.test.near" } }
+            rules = []
+        "#,
+        )
+        .unwrap();
+        let registry = build_registry(Some(&config_path)).unwrap();
+        let names: Vec<_> = registry.handlers().map(|handler| handler.name()).collect();
+        assert_eq!(names, vec!["WRAP LOCALNET"]);
+
+        std::fs::write(&config_path, "[[contracts]]\nname = \"WRAP LOCALNET\"\n").unwrap();
+        let err = build_registry(Some(&config_path)).err().unwrap();
+        assert!(format!("{:#}", err).starts_with(&format!(
+            "Invalid legacy FT contracts config {}: ",
+            config_path.display()
+        )));
+        assert!(build_registry(Some(&config_dir.path().join("missing.toml"))).is_err());
+    }
+
+    #[test]
+    fn rejects_clashing_event_type_indices() {
+        let config = config::parse_config(
+            r#"
+            [[contracts]]
+            name = "WRAP NEAR"
+            event_type_index = 8
+            accounts = { mainnet = { exact = "wrap.near" } }
+            rules = []
+
+            [[contracts]]
+            name = "WRAP TESTNET"
+            event_type_index = 8
+            accounts = { mainnet = { exact = "wrap.testnet" } }
+            rules = []
+
+            [[contracts]]
+            name = "NEP-245"
+            event_type_index = 9
+            accounts = { mainnet = { exact = "mt.near" } }
+            rules = []
+        "#,
+        )
+        .unwrap();
+        let mut registry = LegacyRegistry::<CoinEvent>::default();
+        let mut errors = vec![];
+        for contract in config.contracts {
+            if let Err(err) = registry.register(Box::new(contract)) {
+                errors.push(err.to_string());
+            }
+        }
+        assert_eq!(
+            errors,
+            vec![
+                "Legacy handlers WRAP NEAR and WRAP TESTNET use the same event type index 8",
+                "Legacy handler NEP-245 uses event type index 9 reserved for the standard events",
+            ]
+        );
+        assert_eq!(registry.handlers().count(), 1);
+    }
+
+    #[test]
+    fn rejects_log_pattern_without_groups() {
+        let config = r#"
+            [[contracts]]
+            name = "WRAP NEAR"
+            event_type_index = 8
+            accounts = { mainnet = { exact = "wrap.near" } }
+
+            [[contracts.rules]]
+            methods = ["near_deposit"]
+            action = "mint_from_logs"
+            log_pattern = '^Deposit (?P<amount>[0-9]+) NEAR$'
+        "#;
+        assert_eq!(
+            config::parse_config(config).unwrap_err().to_string(),
+            "Log pattern of near_deposit in legacy contract WRAP NEAR has no `account_id` group"
+        );
+    }
+
+    const BRIDGE_DAI: &str = "6b175474e89094c44da98b954eedeac495271d0f.factory.bridge.near";
+
+    async fn collect_fixture_events(chain_id: &'static str) -> Vec<CoinEvent> {
+        collect_fixture("legacy_events", |shard_id, outcomes, block_header| {
+            collect_legacy(shard_id, outcomes, block_header, chain_id).boxed_local()
+        })
+        .await
+        .0
+    }
+
+    fn deltas<'a>(
+        events: &'a [CoinEvent],
+        contract_account_id: &str,
+        cause: &str,
+    ) -> Vec<(&'a str, Option<&'a str>, String)> {
+        events
+            .iter()
+            .filter(|event| {
+                event.contract_account_id == contract_account_id && event.cause == cause
+            })
+            .map(|event| {
+                (
+                    event.affected_account_id.as_str(),
+                    event.involved_account_id.as_deref(),
+                    event.delta_amount.to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn mints_by_each_action() {
+        let events = collect_fixture_events("mainnet").await;
+        // mint_from_args
+        assert_eq!(
+            deltas(&events, BRIDGE_DAI, "MINT"),
+            vec![("alice.near", None, "100".to_string())]
+        );
+        assert_eq!(
+            deltas(&events, "token.skyward.near", "MINT"),
+            vec![("skyward.near", None, "1000000".to_string())]
+        );
+        // mint_from_args at `new` and mint_from_deposit
+        assert_eq!(
+            deltas(&events, "abc.tkn.near", "MINT"),
+            vec![
+                ("alice.near", None, "700".to_string()),
+                ("bob.near", None, "9".to_string())
+            ]
+        );
+        // mint_from_args at `ft_on_transfer` and mint_from_deposit
+        assert_eq!(
+            deltas(&events, "x.wentokensir.near", "MINT"),
+            vec![
+                ("alice.near", None, "6".to_string()),
+                ("carol.near", None, "4".to_string())
+            ]
+        );
+        // mint_from_logs, the zero amounts are skipped
+        assert_eq!(
+            deltas(&events, "wrap.near", "MINT"),
+            vec![("alice.near", None, "5000".to_string())]
+        );
+        assert_eq!(
+            deltas(&events, "aurora", "MINT"),
+            vec![("alice.near", None, "10".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn transfers_and_refunds() {
+        let events = collect_fixture_events("mainnet").await;
+        // transfer, transfer with the call and the refund of its unused part
+        assert_eq!(
+            deltas(&events, "wrap.near", "TRANSFER"),
+            vec![
+                ("alice.near", Some("bob.near"), "-2000".to_string()),
+                ("bob.near", Some("alice.near"), "2000".to_string()),
+                (
+                    "alice.near",
+                    Some("v2.ref-finance.near"),
+                    "-3000".to_string()
+                ),
+                (
+                    "v2.ref-finance.near",
+                    Some("alice.near"),
+                    "3000".to_string()
+                ),
+                (
+                    "v2.ref-finance.near",
+                    Some("alice.near"),
+                    "-2000".to_string()
+                ),
+                (
+                    "alice.near",
+                    Some("v2.ref-finance.near"),
+                    "2000".to_string()
+                ),
+            ]
+        );
+        let memos: Vec<_> = events
+            .iter()
+            .filter(|event| event.receipt_id == "E3W8wgePQfg7EpZX2kCDXe17sk8uUrDQcE4vD3pnccEh")
+            .map(|event| event.event_memo.as_deref())
+            .collect();
+        assert_eq!(memos, vec![Some("hi"), Some("hi")]);
+        // refund_from_logs and the transfer with borsh arguments
+        assert_eq!(
+            deltas(&events, "aurora", "TRANSFER"),
+            vec![
+                ("bob.near", Some("alice.near"), "-4".to_string()),
+                ("alice.near", Some("bob.near"), "4".to_string()),
+                ("carol.near", Some("dave.near"), "-3".to_string()),
+                ("dave.near", Some("carol.near"), "3".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn burns_by_each_action() {
+        let events = collect_fixture_events("mainnet").await;
+        assert_eq!(
+            deltas(&events, "wrap.near", "BURN"),
+            vec![("bob.near", None, "-500".to_string())]
+        );
+        // The withdrawal with borsh arguments
+        assert_eq!(
+            deltas(&events, "aurora", "BURN"),
+            vec![("carol.near", None, "-7".to_string())]
+        );
+        // The refund to the deleted sender is burnt
+        assert_eq!(
+            deltas(&events, BRIDGE_DAI, "BURN"),
+            vec![
+                ("alice.near", None, "-30".to_string()),
+                ("bob.near", None, "-5".to_string())
+            ]
+        );
+    }
+
+    // NEP-141 events, failed receipts, ignored and unknown methods, the accounts which look similar to the legacy ones
+    #[tokio::test]
+    async fn skips_receipts_without_legacy_activity() {
+        let events = collect_fixture_events("mainnet").await;
+        for receipt_id in [
+            "4WWZTZpQohvCL89MQYj5hKvuDdNvHyPhABoGer3829nP",
+            "G4HxqTVMME4gj8p56SYRajZqdxx7knQt1gKwvkCAtD8u",
+            "GXopGmxt7vBHRooGUQRWYKJg9hswKuS9rzymiK2fqvsZ",
+            "EenBp2FHxku2QvJqEjRPVuNu5iX8hRXKRPWQC1FjpSdQ",
+            "BQfSyNKaGtNJM4p7MYgsNtnmKjuSumt4qgYVjPGyhYg2",
+            "JAX9G2Vid9e7puE35TraBQKK1enzmxzfNLA4f62FQQQE",
+            "Hb5iJxYttnooe1wLVZi8MSxPesYgM29hryb5aP69ZgbK",
+        ] {
+            assert!(
+                events.iter().all(|event| event.receipt_id != receipt_id),
+                "{} should be skipped",
+                receipt_id
+            );
+        }
+        assert!(events.iter().all(|event| event.status == "SUCCESS"));
+    }
+
+    // The event type of each contract goes to its event_index, so the indexes do not clash
+    #[tokio::test]
+    async fn uses_event_type_of_each_contract() {
+        let events = collect_fixture_events("mainnet").await;
+        let mut event_types: Vec<_> = events
+            .iter()
+            .map(|event| {
+                let event_index = event.event_index.to_string();
+                (
+                    event.contract_account_id.as_str(),
+                    event_index[event_index.len() - 9..event_index.len() - 6].to_string(),
+                )
+            })
+            .collect();
+        event_types.dedup();
+        assert_eq!(
+            event_types,
+            vec![
+                ("aurora", "003".to_string()),
+                (BRIDGE_DAI, "004".to_string()),
+                ("token.skyward.near", "005".to_string()),
+                ("abc.tkn.near", "006".to_string()),
+                ("x.wentokensir.near", "007".to_string()),
+                ("wrap.near", "008".to_string()),
+            ]
+        );
+        for event in &events {
+            assert_eq!(event.block_height, BigDecimal::from(75000006));
+            assert_eq!(event.standard, coin::FT_LEGACY);
+            assert_eq!(event.event_version, None);
+        }
+    }
+
+    // `aurora` is the only account of the fixture which has the same name on testnet
+    #[tokio::test]
+    async fn handles_contracts_of_the_chain() {
+        let mainnet_event_indexes: Vec<_> = collect_fixture_events("mainnet")
+            .await
+            .into_iter()
+            .filter(|event| event.contract_account_id == "aurora")
+            .map(|event| event.event_index)
+            .collect();
+        let testnet_event_indexes: Vec<_> = collect_fixture_events("testnet")
+            .await
+            .into_iter()
+            .map(|event| event.event_index)
+            .collect();
+        assert_eq!(testnet_event_indexes, mainnet_event_indexes);
+        assert_eq!(testnet_event_indexes.len(), 6);
+        assert!(collect_fixture_events("localnet").await.is_empty());
+    }
 }
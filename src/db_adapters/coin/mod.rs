@@ -1,15 +1,21 @@
+use crate::configs::{EventVersionMode, InsertMethod};
 use crate::db_adapters::Event;
 use crate::models;
 use crate::models::coin_events::CoinEvent;
+use crate::models::rejected_events::RejectedEvent;
 use bigdecimal::BigDecimal;
 use futures::future::try_join_all;
 use futures::try_join;
+use near_jsonrpc_client::JsonRpcClient;
 use near_lake_framework::near_indexer_primitives;
 use near_primitives::types::AccountId;
 use num_traits::Zero;
 
-mod legacy;
+pub(crate) mod absolute_amounts;
+pub(crate) mod ft_balances;
+pub(crate) mod legacy;
 mod nep141_events;
+pub(crate) mod reconciliation;
 
 pub const FT: &str = "FT_NEP141";
 pub const FT_LEGACY: &str = "FT_LEGACY";
@@ -22,21 +28,49 @@ struct FtEvent {
     pub memo: Option<String>,
 }
 
-pub(crate) async fn store_ft(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+pub(crate) async fn collect_ft(
     streamer_message: &near_indexer_primitives::StreamerMessage,
     chain_id: &str,
-) -> anyhow::Result<()> {
+    event_version_mode: EventVersionMode,
+) -> anyhow::Result<(Vec<CoinEvent>, Vec<RejectedEvent>)> {
     let mut events: Vec<CoinEvent> = vec![];
+    let mut rejected_events: Vec<RejectedEvent> = vec![];
 
     let events_futures = streamer_message
         .shards
         .iter()
-        .map(|shard| collect_ft_for_shard(streamer_message, shard, chain_id));
-    for events_by_shard in try_join_all(events_futures).await? {
+        .map(|shard| collect_ft_for_shard(streamer_message, shard, chain_id, event_version_mode));
+    for (events_by_shard, rejected_events_by_shard) in try_join_all(events_futures).await? {
         events.extend(events_by_shard);
+        rejected_events.extend(rejected_events_by_shard);
+    }
+    Ok((events, rejected_events))
+}
+
+// Absolute amounts and current balances depend on all the previous blocks,
+// so the events are stored in the order of the blocks
+pub(crate) async fn store_ft(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    absolute_amounts_rpc_client: Option<&JsonRpcClient>,
+    balances: &mut absolute_amounts::Balances,
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    events: &mut [CoinEvent],
+    insert_method: InsertMethod,
+    update_ft_balances: bool,
+) -> anyhow::Result<absolute_amounts::BlockBalances> {
+    // `absolute_amount` stays NULL if it's not enabled
+    let block_balances = match absolute_amounts_rpc_client {
+        Some(rpc_client) => {
+            absolute_amounts::fill_absolute_amounts(rpc_client, balances, block_header, events)
+                .await?
+        }
+        None => absolute_amounts::BlockBalances::default(),
+    };
+    models::insert(tx, events, insert_method).await?;
+    if update_ft_balances {
+        ft_balances::store_ft_balances(tx, events, insert_method).await?;
     }
-    models::chunked_insert(pool, &events).await
+    Ok(block_balances)
 }
 
 pub(crate) fn filter_zeros_and_enumerate_events(
@@ -57,13 +91,15 @@ async fn collect_ft_for_shard(
     streamer_message: &near_indexer_primitives::StreamerMessage,
     shard: &near_indexer_primitives::IndexerShard,
     chain_id: &str,
-) -> anyhow::Result<Vec<CoinEvent>> {
+    event_version_mode: EventVersionMode,
+) -> anyhow::Result<(Vec<CoinEvent>, Vec<RejectedEvent>)> {
     let mut events: Vec<CoinEvent> = vec![];
 
     let nep141_future = nep141_events::collect_nep141_events(
         &shard.shard_id,
         &shard.receipt_execution_outcomes,
         &streamer_message.block.header,
+        event_version_mode,
     );
     let legacy_contracts_future = legacy::collect_legacy(
         &shard.shard_id,
@@ -71,11 +107,13 @@ async fn collect_ft_for_shard(
         &streamer_message.block.header,
         chain_id,
     );
-    let (nep141_events, legacy_events) = try_join!(nep141_future, legacy_contracts_future)?;
+    let ((nep141_events, mut rejected_events), (legacy_events, rejected_legacy_events)) =
+        try_join!(nep141_future, legacy_contracts_future)?;
 
     events.extend(nep141_events);
     events.extend(legacy_events);
-    Ok(events)
+    rejected_events.extend(rejected_legacy_events);
+    Ok((events, rejected_events))
 }
 
 async fn build_event(
@@ -96,5 +134,8 @@ async fn build_event(
         cause: custom.cause,
         status: crate::db_adapters::get_status(&base.status),
         event_memo: custom.memo,
+        absolute_amount: None, // initialized later
+        event_version: None,   // NEP-141 events have it initialized later
+        unsupported_event_version: false,
     })
 }
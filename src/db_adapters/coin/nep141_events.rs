@@ -1,25 +1,46 @@
+use crate::configs::EventVersionMode;
 use crate::db_adapters::event_types;
 use crate::db_adapters::event_types::Nep141Event;
+use crate::db_adapters::rejected_events::build_rejected_event;
 use crate::db_adapters::Event;
 use crate::db_adapters::{coin, events, get_base};
 use crate::models::coin_events::CoinEvent;
+use crate::models::rejected_events::RejectedEvent;
 use bigdecimal::BigDecimal;
 use near_lake_framework::near_indexer_primitives;
 use near_primitives::types::AccountId;
 use std::ops::Mul;
 use std::str::FromStr;
 
+// The events with invalid values are rejected, the rest of the block is still stored
 pub(crate) async fn collect_nep141_events(
     shard_id: &near_indexer_primitives::types::ShardId,
     receipt_execution_outcomes: &[near_indexer_primitives::IndexerExecutionOutcomeWithReceipt],
     block_header: &near_indexer_primitives::views::BlockHeaderView,
-) -> anyhow::Result<Vec<CoinEvent>> {
+    event_version_mode: EventVersionMode,
+) -> anyhow::Result<(Vec<CoinEvent>, Vec<RejectedEvent>)> {
     let mut res = Vec::new();
+    let mut rejected_events = Vec::new();
     for outcome in receipt_execution_outcomes {
-        let events = events::extract_events(outcome);
-        for event in events {
-            if let event_types::NearEvent::Nep141(ft_events) = event {
-                res.extend(compose_db_events(&ft_events, outcome, block_header).await?);
+        let events = events::extract_events_with_index(outcome, event_version_mode);
+        for indexed_event in events {
+            if let event_types::NearEvent::Nep141(ft_events) = &indexed_event.event {
+                match compose_db_events(ft_events, outcome, block_header).await {
+                    Ok(mut coin_events) => {
+                        for ft_event in &mut coin_events {
+                            ft_event.event_version = Some(ft_events.version.clone());
+                            ft_event.unsupported_event_version =
+                                !indexed_event.is_version_supported;
+                        }
+                        res.extend(coin_events);
+                    }
+                    Err(err) => rejected_events.push(build_rejected_event(
+                        outcome,
+                        block_header,
+                        indexed_event.log_index,
+                        &err,
+                    )?),
+                }
             }
         }
     }
@@ -30,7 +51,7 @@ pub(crate) async fn collect_nep141_events(
         &Event::Nep141,
     )?;
 
-    Ok(res)
+    Ok((res, rejected_events))
 }
 
 async fn compose_db_events(
@@ -49,9 +70,9 @@ async fn compose_db_events(
                 }
                 let base = get_base(Event::Nep141, outcome, block_header)?;
                 let custom = coin::FtEvent {
-                    affected_id: AccountId::from_str(&mint_event.owner_id)?,
+                    affected_id: parse_account_id(&mint_event.owner_id)?,
                     involved_id: None,
-                    delta: BigDecimal::from_str(&mint_event.amount)?,
+                    delta: parse_amount(&mint_event.amount)?,
                     cause: "MINT".to_string(),
                     memo: mint_event
                         .memo
@@ -65,9 +86,9 @@ async fn compose_db_events(
             for transfer_event in transfer_events {
                 let base = get_base(Event::Nep141, outcome, block_header)?;
                 let custom = coin::FtEvent {
-                    affected_id: AccountId::from_str(&transfer_event.old_owner_id)?,
-                    involved_id: Some(AccountId::from_str(&transfer_event.new_owner_id)?),
-                    delta: BigDecimal::from_str(&transfer_event.amount)?.mul(BigDecimal::from(-1)),
+                    affected_id: parse_account_id(&transfer_event.old_owner_id)?,
+                    involved_id: Some(parse_account_id(&transfer_event.new_owner_id)?),
+                    delta: parse_amount(&transfer_event.amount)?.mul(BigDecimal::from(-1)),
                     cause: "TRANSFER".to_string(),
                     memo: transfer_event
                         .memo
@@ -78,9 +99,9 @@ async fn compose_db_events(
 
                 let base = get_base(Event::Nep141, outcome, block_header)?;
                 let custom = coin::FtEvent {
-                    affected_id: AccountId::from_str(&transfer_event.new_owner_id)?,
-                    involved_id: Some(AccountId::from_str(&transfer_event.old_owner_id)?),
-                    delta: BigDecimal::from_str(&transfer_event.amount)?,
+                    affected_id: parse_account_id(&transfer_event.new_owner_id)?,
+                    involved_id: Some(parse_account_id(&transfer_event.old_owner_id)?),
+                    delta: parse_amount(&transfer_event.amount)?,
                     cause: "TRANSFER".to_string(),
                     memo: transfer_event
                         .memo
@@ -94,9 +115,9 @@ async fn compose_db_events(
             for burn_event in burn_events {
                 let base = get_base(Event::Nep141, outcome, block_header)?;
                 let custom = coin::FtEvent {
-                    affected_id: AccountId::from_str(&burn_event.owner_id)?,
+                    affected_id: parse_account_id(&burn_event.owner_id)?,
                     involved_id: None,
-                    delta: BigDecimal::from_str(&burn_event.amount)?.mul(BigDecimal::from(-1)),
+                    delta: parse_amount(&burn_event.amount)?.mul(BigDecimal::from(-1)),
                     cause: "BURN".to_string(),
                     memo: burn_event
                         .memo
@@ -110,3 +131,175 @@ async fn compose_db_events(
 
     Ok(ft_events)
 }
+
+fn parse_account_id(account_id: &str) -> anyhow::Result<AccountId> {
+    AccountId::from_str(account_id)
+        .map_err(|err| anyhow::anyhow!("Invalid account_id {}: {}", account_id, err))
+}
+
+fn parse_amount(amount: &str) -> anyhow::Result<BigDecimal> {
+    BigDecimal::from_str(amount)
+        .map_err(|err| anyhow::anyhow!("Invalid amount {}: {}", amount, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_adapters::test_utils::collect_fixture;
+    use futures::FutureExt;
+
+    // The fixture also has zero mint, the plain log and NEP-171 event, they give no rows
+    async fn collect_fixture_events() -> (Vec<CoinEvent>, Vec<RejectedEvent>) {
+        collect_fixture("nep141_events", |shard_id, outcomes, block_header| {
+            collect_nep141_events(shard_id, outcomes, block_header, EventVersionMode::Strict)
+                .boxed_local()
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn enumerates_events_of_the_block() {
+        let (events, _) = collect_fixture_events().await;
+        let event_indexes: Vec<_> = events
+            .iter()
+            .map(|event| event.event_index.to_string())
+            .collect();
+        assert_eq!(
+            event_indexes,
+            (0..6)
+                .map(|index| format!("1735000001000000000000000000100000{}", index))
+                .collect::<Vec<_>>()
+        );
+        for event in &events {
+            assert_eq!(event.block_height, BigDecimal::from(75000001));
+            assert_eq!(
+                event.block_timestamp,
+                BigDecimal::from(1735000001000000000u64)
+            );
+            assert_eq!(event.standard, coin::FT);
+            assert_eq!(event.absolute_amount, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn collects_mint_and_burn() {
+        let (events, _) = collect_fixture_events().await;
+        let rows: Vec<_> = events
+            .iter()
+            .filter(|event| event.cause != "TRANSFER")
+            .map(|event| {
+                (
+                    event.cause.as_str(),
+                    event.affected_account_id.as_str(),
+                    event.delta_amount.to_string(),
+                    event.event_memo.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "MINT",
+                    "alice.near",
+                    "1000000".to_string(),
+                    Some("initial supply")
+                ),
+                ("BURN", "bob.near", "-50".to_string(), None),
+            ]
+        );
+    }
+
+    // Each side of the transfer has its own row
+    #[tokio::test]
+    async fn splits_transfer() {
+        let (events, _) = collect_fixture_events().await;
+        let rows: Vec<_> = events
+            .iter()
+            .filter(|event| event.receipt_id == "DjzQ5EZxGieowUWQjX3daAH8tL5XWvCrV26erE7XyVbt")
+            .map(|event| {
+                (
+                    event.affected_account_id.as_str(),
+                    event.involved_account_id.as_deref(),
+                    event.delta_amount.to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("alice.near", Some("bob.near"), "-250".to_string()),
+                ("bob.near", Some("alice.near"), "250".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_events_of_failed_receipts() {
+        let (events, _) = collect_fixture_events().await;
+        let failed_receipts: Vec<_> = events
+            .iter()
+            .filter(|event| event.status == "FAILURE")
+            .map(|event| event.receipt_id.as_str())
+            .collect();
+        assert_eq!(
+            failed_receipts,
+            vec![
+                "34o6rgbf91dUku2bErcGymVh2dJhcnBRcCE3jD9BJ2Vo",
+                "34o6rgbf91dUku2bErcGymVh2dJhcnBRcCE3jD9BJ2Vo",
+            ]
+        );
+    }
+
+    // One invalid account rejects the whole event, the valid parts of it are not stored as well
+    #[tokio::test]
+    async fn rejects_event_with_invalid_account() {
+        let (events, rejected_events) = collect_fixture_events().await;
+        let rejected_receipt = "FPgARJqfBLfEBKSza6yNvqKnX9pPaRTHMZSjvdonVRHY";
+        assert!(events
+            .iter()
+            .all(|event| event.receipt_id != rejected_receipt));
+        let errors: Vec<_> = rejected_events
+            .iter()
+            .map(|event| (event.receipt_id.as_str(), event.error.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![(
+                rejected_receipt,
+                "Invalid account_id Invalid Account!: the Account ID contains an invalid character 'I' at index 0",
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_event_versions() {
+        let (events, rejected_events) =
+            collect_fixture("event_versions", |shard_id, outcomes, block_header| {
+                collect_nep141_events(shard_id, outcomes, block_header, EventVersionMode::Lenient)
+                    .boxed_local()
+            })
+            .await;
+
+        let rows: Vec<_> = events
+            .iter()
+            .map(|event| {
+                (
+                    event.affected_account_id.as_str(),
+                    event.event_version.as_deref(),
+                    event.unsupported_event_version,
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("alice.near", Some("1.0.0"), false),
+                ("bob.near", Some("1.0.0"), false),
+                ("alice.near", Some("2.0.0"), true),
+                ("carol.near", Some("2.0.0"), true),
+            ]
+        );
+        assert!(rejected_events.is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use futures::{StreamExt, TryStreamExt};
+use near_jsonrpc_client::{methods, JsonRpcClient};
+use near_lake_framework::near_indexer_primitives;
+use num_traits::Zero;
+
+use crate::models::coin_events::CoinEvent;
+use crate::AccountWithContract;
+
+// Each block may touch hundreds of accounts, we don't want to flood the RPC
+const RPC_CONCURRENCY: usize = 10;
+
+// For each account affected in the block, the balance before the block plus all the deltas
+// should give the balance after the block. Otherwise, the events of the contract are not trusted.
+// The balances which can't be queried (e.g. the contract has no `ft_balance_of`) are skipped
+pub(crate) async fn find_inconsistent_contracts(
+    rpc_client: &JsonRpcClient,
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    coin_events: &[CoinEvent],
+) -> anyhow::Result<HashSet<String>> {
+    let mut deltas: HashMap<AccountWithContract, BigDecimal> = HashMap::new();
+    // The failed receipts do not change the balances
+    for event in coin_events.iter().filter(|event| event.status == "SUCCESS") {
+        let account_with_contract = AccountWithContract {
+            account_id: event.affected_account_id.parse()?,
+            contract_account_id: event.contract_account_id.parse()?,
+        };
+        *deltas
+            .entry(account_with_contract)
+            .or_insert_with(BigDecimal::zero) += &event.delta_amount;
+    }
+
+    let mut checks = futures::stream::iter(deltas.into_iter().map(
+        |(account_with_contract, delta)| async move {
+            let is_consistent =
+                is_balance_consistent(rpc_client, block_header, &account_with_contract, &delta)
+                    .await?;
+            anyhow::Ok((account_with_contract, is_consistent))
+        },
+    ))
+    .buffer_unordered(RPC_CONCURRENCY);
+
+    let mut inconsistent_contracts = HashSet::new();
+    while let Some((account_with_contract, is_consistent)) = checks.try_next().await? {
+        if !is_consistent {
+            inconsistent_contracts.insert(account_with_contract.contract_account_id.to_string());
+        }
+    }
+    Ok(inconsistent_contracts)
+}
+
+async fn is_balance_consistent(
+    rpc_client: &JsonRpcClient,
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    account_with_contract: &AccountWithContract,
+    delta: &BigDecimal,
+) -> anyhow::Result<bool> {
+    let balance_before =
+        match get_ft_balance(rpc_client, account_with_contract, &block_header.prev_hash).await? {
+            Some(balance) => balance,
+            None => return Ok(true),
+        };
+    let balance_after =
+        match get_ft_balance(rpc_client, account_with_contract, &block_header.hash).await? {
+            Some(balance) => balance,
+            None => return Ok(true),
+        };
+
+    if &balance_before + delta == balance_after {
+        return Ok(true);
+    }
+    tracing::warn!(
+        target: crate::LOGGING_PREFIX,
+        "Inconsistent balance of {} in {} at block {}: {} before, {} in the events, {} after",
+        account_with_contract.account_id,
+        account_with_contract.contract_account_id,
+        block_header.height,
+        balance_before,
+        delta,
+        balance_after
+    );
+    Ok(false)
+}
+
+/// FT balance at the end of the block.
+/// The contract which does not exist yet has zero balances, it could be deployed and mint the coins in the next block.
+/// `None` means the contract can't answer: `ft_balance_of` fails or returns garbage
+pub(crate) async fn get_ft_balance(
+    rpc_client: &JsonRpcClient,
+    account_with_contract: &AccountWithContract,
+    block_hash: &near_indexer_primitives::CryptoHash,
+) -> anyhow::Result<Option<BigDecimal>> {
+    // The client is built on another version of near-primitives, so we talk to it in plain JSON
+    let request = methods::any::<Result<serde_json::Value, serde_json::Value>>(
+        "query",
+        serde_json::json!({
+            "request_type": "call_function",
+            "block_id": block_hash.to_string(),
+            "account_id": account_with_contract.contract_account_id,
+            "method_name": "ft_balance_of",
+            "args_base64": base64::encode(
+                &serde_json::json!({ "account_id": account_with_contract.account_id }).to_string()
+            ),
+        }),
+    );
+
+    let response = match rpc_client.call(request).await {
+        Ok(response) => response,
+        Err(err) => {
+            return match err.handler_error().and_then(|error| error["name"].as_str()) {
+                Some("UNKNOWN_ACCOUNT" | "NO_CONTRACT_CODE") => Ok(Some(BigDecimal::zero())),
+                Some("CONTRACT_EXECUTION_ERROR") => Ok(None),
+                _ => Err(anyhow::anyhow!(
+                    "Failed to query ft_balance_of({}) in {} at block {}: {}",
+                    account_with_contract.account_id,
+                    account_with_contract.contract_account_id,
+                    block_hash,
+                    err
+                )),
+            }
+        }
+    };
+    // The contract execution errors are still returned as a successful response with `error` field
+    if response.get("error").is_some() {
+        return Ok(None);
+    }
+    // The contract may return anything, it's not a reason to stop the indexer
+    Ok(
+        serde_json::from_value::<Vec<u8>>(response["result"].clone())
+            .ok()
+            .and_then(|result| serde_json::from_slice::<String>(&result).ok())
+            .and_then(|balance| BigDecimal::from_str(&balance).ok()),
+    )
+}
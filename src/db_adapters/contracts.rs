@@ -0,0 +1,85 @@
+use std::collections::{BTreeMap, HashSet};
+
+use bigdecimal::BigDecimal;
+
+use crate::configs::InsertMethod;
+use crate::models;
+use crate::models::coin_events::CoinEvent;
+use crate::models::contracts::Contract;
+use crate::models::mt_events::MtEvent;
+use crate::models::nft_events::NftEvent;
+
+// The contracts are shared between the blocks, so this is called in the order of the blocks,
+// see `db_adapters::events::finish_block`.
+// The rows are sorted by contract_account_id: the concurrent writers always lock them in the same order
+pub(crate) async fn store_contracts(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    coin_events: &[CoinEvent],
+    nft_events: &[NftEvent],
+    mt_events: &[MtEvent],
+    inconsistent_contracts: &HashSet<String>,
+    insert_method: InsertMethod,
+) -> anyhow::Result<()> {
+    // One upsert can't touch the same row twice, so we keep only the first event of each contract
+    let mut first_events: BTreeMap<&str, (&BigDecimal, Contract)> = BTreeMap::new();
+    let events = coin_events
+        .iter()
+        .map(|event| {
+            (
+                &event.event_index,
+                &event.contract_account_id,
+                &event.standard,
+                &event.block_timestamp,
+                &event.block_height,
+            )
+        })
+        .chain(nft_events.iter().map(|event| {
+            (
+                &event.event_index,
+                &event.contract_account_id,
+                &event.standard,
+                &event.block_timestamp,
+                &event.block_height,
+            )
+        }))
+        .chain(mt_events.iter().map(|event| {
+            (
+                &event.event_index,
+                &event.contract_account_id,
+                &event.standard,
+                &event.block_timestamp,
+                &event.block_height,
+            )
+        }));
+    for (event_index, contract_account_id, standard, block_timestamp, block_height) in events {
+        match first_events.get(contract_account_id.as_str()) {
+            Some((first_event_index, _)) if *first_event_index <= event_index => {}
+            _ => {
+                first_events.insert(
+                    contract_account_id,
+                    (
+                        event_index,
+                        Contract {
+                            contract_account_id: contract_account_id.clone(),
+                            standard: standard.clone(),
+                            first_event_at_timestamp: block_timestamp.clone(),
+                            first_event_at_block_height: block_height.clone(),
+                            inconsistency_found_at_timestamp: inconsistent_contracts
+                                .contains(contract_account_id)
+                                .then(|| block_timestamp.clone()),
+                            inconsistency_found_at_block_height: inconsistent_contracts
+                                .contains(contract_account_id)
+                                .then(|| block_height.clone()),
+                        },
+                    ),
+                );
+            }
+        }
+    }
+
+    let contracts: Vec<Contract> = first_events
+        .into_values()
+        .map(|(_, contract)| contract)
+        .collect();
+    models::insert(tx, &contracts, insert_method).await
+}
@@ -1,11 +1,79 @@
 use serde::{Deserialize, Serialize};
 
+lazy_static! {
+    /// The standards of `NearEvent` and the versions we know how to parse.
+    /// The events of the other standards are not parsed
+    pub(crate) static ref SUPPORTED_VERSIONS: Vec<(&'static str, semver::VersionReq)> = [
+        ("nep141", ">=1.0.0, <2.0.0"),
+        ("nep171", ">=1.0.0, <2.0.0"),
+        ("nep245", ">=1.0.0, <2.0.0"),
+    ]
+    .into_iter()
+    .map(|(standard, supported_versions)| {
+        (
+            standard,
+            semver::VersionReq::parse(supported_versions)
+                .expect("Invalid supported versions of the events"),
+        )
+    })
+    .collect();
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(tag = "standard")]
 #[serde(rename_all = "snake_case")]
 pub(crate) enum NearEvent {
     Nep141(Nep141Event),
     Nep171(Nep171Event),
+    Nep245(Nep245Event),
+}
+
+impl NearEvent {
+    pub fn standard(&self) -> &'static str {
+        match self {
+            NearEvent::Nep141(_) => "nep141",
+            NearEvent::Nep171(_) => "nep171",
+            NearEvent::Nep245(_) => "nep245",
+        }
+    }
+
+    pub fn version(&self) -> &str {
+        match self {
+            NearEvent::Nep141(event) => &event.version,
+            NearEvent::Nep171(event) => &event.version,
+            NearEvent::Nep245(event) => &event.version,
+        }
+    }
+}
+
+/// `Err` explains why the version is not supported
+pub(crate) fn check_version(standard: &str, version: &str) -> Result<(), String> {
+    let supported_versions = match SUPPORTED_VERSIONS
+        .iter()
+        .find(|(known_standard, _)| *known_standard == standard)
+    {
+        Some((_, supported_versions)) => supported_versions,
+        None => return Err(format!("Unknown standard {}", standard)),
+    };
+    let version = semver::Version::parse(version)
+        .map_err(|err| format!("Invalid {} event version {}: {}", standard, version, err))?;
+    if supported_versions.matches(&version) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unsupported {} event version {}, expected {}",
+            standard, version, supported_versions
+        ))
+    }
+}
+
+/// Any NEP-297 event, the standard may be unknown to us
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct RawEvent {
+    pub standard: String,
+    pub version: String,
+    pub event: String,
+    pub data: Option<serde_json::Value>,
 }
 
 // *** NEP-141 FT ***
@@ -64,6 +132,10 @@ pub(crate) enum Nep171EventKind {
     NftMint(Vec<NftMintData>),
     NftTransfer(Vec<NftTransferData>),
     NftBurn(Vec<NftBurnData>),
+    // NEP-171 v1.1.0
+    ContractMetadataUpdate(Vec<ContractMetadataUpdateData>),
+    // NEP-171 v1.2.0, NEP-177 token metadata is changed
+    NftMetadataUpdate(Vec<NftMetadataUpdateData>),
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -89,3 +161,60 @@ pub(crate) struct NftBurnData {
     pub token_ids: Vec<String>,
     pub memo: Option<String>,
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct ContractMetadataUpdateData {
+    pub memo: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct NftMetadataUpdateData {
+    pub token_ids: Vec<String>,
+    pub memo: Option<String>,
+}
+
+// *** NEP-245 MT ***
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct Nep245Event {
+    pub version: String,
+    #[serde(flatten)]
+    pub event_kind: Nep245EventKind,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+pub(crate) enum Nep245EventKind {
+    MtMint(Vec<MtMintData>),
+    MtTransfer(Vec<MtTransferData>),
+    MtBurn(Vec<MtBurnData>),
+}
+
+// `amounts` go in the same order as `token_ids`
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct MtMintData {
+    pub owner_id: String,
+    pub token_ids: Vec<String>,
+    pub amounts: Vec<String>,
+    pub memo: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct MtTransferData {
+    pub authorized_id: Option<String>,
+    pub old_owner_id: String,
+    pub new_owner_id: String,
+    pub token_ids: Vec<String>,
+    pub amounts: Vec<String>,
+    pub memo: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct MtBurnData {
+    pub authorized_id: Option<String>,
+    pub owner_id: String,
+    pub token_ids: Vec<String>,
+    pub amounts: Vec<String>,
+    pub memo: Option<String>,
+}
@@ -1,25 +1,237 @@
+use crate::configs::{EventVersionMode, InsertMethod};
 use crate::db_adapters::event_types;
-use crate::db_adapters::{coin, nft};
-use futures::try_join;
+use crate::db_adapters::{coin, contracts, mt, nft, raw_events, rejected_events};
+use crate::models::coin_events::CoinEvent;
+use crate::models::mt_events::MtEvent;
+use crate::models::nft_events::NftEvent;
+use near_jsonrpc_client::JsonRpcClient;
 use near_lake_framework::near_indexer_primitives;
+use std::collections::HashSet;
 
+/// The block with the events written to the uncommitted transaction.
+/// FT events are not written yet, see `finish_block`
+pub(crate) struct PendingBlock {
+    pub block_height: u64,
+    pub tx: sqlx::Transaction<'static, sqlx::Postgres>,
+    block_header: near_indexer_primitives::views::BlockHeaderView,
+    coin_events: Vec<CoinEvent>,
+    nft_events: Vec<NftEvent>,
+    mt_events: Vec<MtEvent>,
+    inconsistent_contracts: HashSet<String>,
+}
+
+// All the rows of the block are written in one transaction, so the block is either fully stored or not at all.
+// The transaction is returned uncommitted: the caller finishes the block and commits it in the order of the blocks
 pub(crate) async fn store_events(
     pool: &sqlx::Pool<sqlx::Postgres>,
     streamer_message: &near_indexer_primitives::StreamerMessage,
     chain_id: &str,
-) -> anyhow::Result<()> {
-    try_join!(
-        coin::store_ft(pool, streamer_message, chain_id),
-        nft::store_nft(pool, streamer_message),
-    )?;
-    Ok(())
+    insert_method: InsertMethod,
+    event_version_mode: EventVersionMode,
+    rpc_client: &JsonRpcClient,
+    reconciliation_rpc_client: Option<&JsonRpcClient>,
+) -> anyhow::Result<PendingBlock> {
+    let mut interval = crate::INTERVAL;
+    let mut retry_attempt = 0usize;
+
+    loop {
+        retry_attempt += 1;
+        match try_store_events(
+            pool,
+            streamer_message,
+            chain_id,
+            insert_method,
+            event_version_mode,
+            rpc_client,
+            reconciliation_rpc_client,
+        )
+        .await
+        {
+            Ok(pending_block) => return Ok(pending_block),
+            Err(err) if retry_attempt < crate::db_adapters::RETRY_COUNT => {
+                tracing::warn!(
+                    target: crate::LOGGING_PREFIX,
+                    "Error occurred during storing block {}:\n{:#?}\n Retrying in {} milliseconds...",
+                    streamer_message.block.header.height,
+                    err,
+                    interval.as_millis(),
+                );
+                tokio::time::sleep(interval).await;
+                if interval < crate::MAX_DELAY_TIME {
+                    interval *= 2;
+                }
+            }
+            Err(err) => {
+                return Err(err.context(format!(
+                    "Failed to store block {} after {} attempts. Stop trying.",
+                    streamer_message.block.header.height, retry_attempt
+                )))
+            }
+        }
+    }
+}
+
+// The failed transaction is rolled back on drop
+async fn try_store_events(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    streamer_message: &near_indexer_primitives::StreamerMessage,
+    chain_id: &str,
+    insert_method: InsertMethod,
+    event_version_mode: EventVersionMode,
+    rpc_client: &JsonRpcClient,
+    reconciliation_rpc_client: Option<&JsonRpcClient>,
+) -> anyhow::Result<PendingBlock> {
+    let mut tx = pool.begin().await?;
+    let (coin_events, mut rejected_events) =
+        coin::collect_ft(streamer_message, chain_id, event_version_mode).await?;
+    let (nft_events, rejected_nft_events) = nft::store_nft(
+        &mut tx,
+        streamer_message,
+        chain_id,
+        insert_method,
+        event_version_mode,
+        rpc_client,
+    )
+    .await?;
+    rejected_events.extend(rejected_nft_events);
+    let (mt_events, rejected_mt_events) =
+        mt::store_mt(&mut tx, streamer_message, insert_method, event_version_mode).await?;
+    rejected_events.extend(rejected_mt_events);
+    raw_events::store_raw_events(&mut tx, streamer_message, insert_method).await?;
+    rejected_events::store_rejected_events(
+        &mut tx,
+        streamer_message,
+        rejected_events,
+        insert_method,
+        event_version_mode,
+    )
+    .await?;
+    let inconsistent_contracts = match reconciliation_rpc_client {
+        Some(rpc_client) => {
+            coin::reconciliation::find_inconsistent_contracts(
+                rpc_client,
+                &streamer_message.block.header,
+                &coin_events,
+            )
+            .await?
+        }
+        None => HashSet::new(),
+    };
+    Ok(PendingBlock {
+        block_height: streamer_message.block.header.height,
+        tx,
+        block_header: streamer_message.block.header.clone(),
+        coin_events,
+        nft_events,
+        mt_events,
+        inconsistent_contracts,
+    })
+}
+
+// The rows shared between the blocks and the rows depending on the previous blocks are written in the order of the blocks.
+// Otherwise the uncommitted transaction of the next block could lock the shared rows for the previous one forever.
+// The returned balances go to `balances` after the commit.
+// `ft_balances` is updated only if the blocks are stored in their order, see `ft_balances::store_ft_balances`
+pub(crate) async fn finish_block(
+    pending_block: &mut PendingBlock,
+    absolute_amounts_rpc_client: Option<&JsonRpcClient>,
+    balances: &mut coin::absolute_amounts::Balances,
+    insert_method: InsertMethod,
+    update_ft_balances: bool,
+) -> anyhow::Result<coin::absolute_amounts::BlockBalances> {
+    let block_balances = coin::store_ft(
+        &mut pending_block.tx,
+        absolute_amounts_rpc_client,
+        balances,
+        &pending_block.block_header,
+        &mut pending_block.coin_events,
+        insert_method,
+        update_ft_balances,
+    )
+    .await?;
+    nft::ownership::store_nft_ownership(
+        &mut pending_block.tx,
+        &pending_block.nft_events,
+        insert_method,
+    )
+    .await?;
+    contracts::store_contracts(
+        &mut pending_block.tx,
+        &pending_block.coin_events,
+        &pending_block.nft_events,
+        &pending_block.mt_events,
+        &pending_block.inconsistent_contracts,
+        insert_method,
+    )
+    .await?;
+    Ok(block_balances)
+}
+
+/// All the well-formed NEP-297 logs together with their positions in the logs of the outcome
+pub(crate) fn extract_raw_events(
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+) -> Vec<(usize, event_types::RawEvent)> {
+    let prefix = "EVENT_JSON:";
+    outcome
+        .execution_outcome
+        .outcome
+        .logs
+        .iter()
+        .enumerate()
+        .filter_map(|(log_index, untrimmed_log)| {
+            let log = untrimmed_log.trim().strip_prefix(prefix)?;
+            // The malformed events are reported by `extract_malformed_events`
+            serde_json::from_str::<event_types::RawEvent>(log.trim())
+                .ok()
+                .map(|event| (log_index, event))
+        })
+        .collect()
 }
 
 pub(crate) fn extract_events(
     outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
 ) -> Vec<event_types::NearEvent> {
+    parse_events(outcome)
+        .into_iter()
+        .map(|(_, event)| event)
+        .collect()
+}
+
+/// The event together with its position in the logs of the outcome
+pub(crate) struct IndexedEvent {
+    pub log_index: usize,
+    pub event: event_types::NearEvent,
+    pub is_version_supported: bool,
+}
+
+/// The same as `extract_events`, the events of unsupported versions are dropped in strict mode
+pub(crate) fn extract_events_with_index(
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    event_version_mode: EventVersionMode,
+) -> Vec<IndexedEvent> {
+    parse_events(outcome)
+        .into_iter()
+        .filter_map(|(log_index, event)| {
+            let is_version_supported =
+                event_types::check_version(event.standard(), event.version()).is_ok();
+            if !is_version_supported && event_version_mode == EventVersionMode::Strict {
+                // It's reported by `extract_malformed_events`
+                return None;
+            }
+            Some(IndexedEvent {
+                log_index,
+                event,
+                is_version_supported,
+            })
+        })
+        .collect()
+}
+
+fn parse_events(
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+) -> Vec<(usize, event_types::NearEvent)> {
     let prefix = "EVENT_JSON:";
-    outcome.execution_outcome.outcome.logs.iter().filter_map(|untrimmed_log| {
+    outcome.execution_outcome.outcome.logs.iter().enumerate().filter_map(|(log_index, untrimmed_log)| {
         let log = untrimmed_log.trim();
         if !log.starts_with(prefix) {
             return None;
@@ -28,11 +240,11 @@ pub(crate) fn extract_events(
         match serde_json::from_str::<'_, event_types::NearEvent>(
             log[prefix.len()..].trim(),
         ) {
-            Ok(result) => Some(result),
+            Ok(result) => Some((log_index, result)),
             Err(err) => {
                 tracing::info!(
                     target: crate::LOGGING_PREFIX,
-                    "Provided event log does not correspond to any of formats defined in NEP. Will ignore this event. \n {:#?} \n{:#?}",
+                    "Provided event log does not correspond to any of formats defined in NEP, it goes to rejected_events if the standard is known. \n {:#?} \n{:#?}",
                     err,
                     untrimmed_log,
                 );
@@ -41,3 +253,134 @@ pub(crate) fn extract_events(
         }
     }).collect()
 }
+
+/// `EVENT_JSON:` logs which are not NEP-297 events, or do not match the known standard they refer to.
+/// In strict mode, the events of unsupported versions are also here.
+/// The events of the unknown standards are not malformed, they are stored only in `raw_events`
+pub(crate) fn extract_malformed_events(
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    event_version_mode: EventVersionMode,
+) -> Vec<(usize, anyhow::Error)> {
+    let prefix = "EVENT_JSON:";
+    outcome
+        .execution_outcome
+        .outcome
+        .logs
+        .iter()
+        .enumerate()
+        .filter_map(|(log_index, untrimmed_log)| {
+            let log = untrimmed_log.trim().strip_prefix(prefix)?.trim();
+            let raw_event = match serde_json::from_str::<event_types::RawEvent>(log) {
+                Ok(raw_event) => raw_event,
+                Err(err) => {
+                    return Some((log_index, anyhow::anyhow!("Not a NEP-297 event: {}", err)))
+                }
+            };
+            if !event_types::SUPPORTED_VERSIONS
+                .iter()
+                .any(|(standard, _)| *standard == raw_event.standard)
+            {
+                return None;
+            }
+            if event_version_mode == EventVersionMode::Strict {
+                if let Err(err) =
+                    event_types::check_version(&raw_event.standard, &raw_event.version)
+                {
+                    return Some((log_index, anyhow::anyhow!(err)));
+                }
+            }
+            serde_json::from_str::<event_types::NearEvent>(log)
+                .err()
+                .map(|err| {
+                    (
+                        log_index,
+                        anyhow::anyhow!("Invalid {} event: {}", raw_event.standard, err),
+                    )
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_adapters::test_utils::read_fixture;
+
+    fn collect_events(
+        event_version_mode: EventVersionMode,
+    ) -> Vec<(usize, &'static str, String, bool)> {
+        let fixture = read_fixture("event_versions");
+        let mut events = vec![];
+        for outcome in &fixture.shard.receipt_execution_outcomes {
+            for indexed_event in extract_events_with_index(outcome, event_version_mode) {
+                events.push((
+                    indexed_event.log_index,
+                    indexed_event.event.standard(),
+                    indexed_event.event.version().to_string(),
+                    indexed_event.is_version_supported,
+                ));
+            }
+        }
+        events
+    }
+
+    fn collect_malformed_events(event_version_mode: EventVersionMode) -> Vec<(usize, String)> {
+        let fixture = read_fixture("event_versions");
+        let mut malformed_events = vec![];
+        for outcome in &fixture.shard.receipt_execution_outcomes {
+            for (log_index, err) in extract_malformed_events(outcome, event_version_mode) {
+                malformed_events.push((log_index, err.to_string()));
+            }
+        }
+        malformed_events
+    }
+
+    // The event of the unknown standard is never checked, it goes only to `raw_events`
+    #[test]
+    fn rejects_unsupported_versions_in_strict_mode() {
+        assert_eq!(
+            collect_events(EventVersionMode::Strict),
+            vec![
+                (0, "nep141", "1.0.0".to_string(), true),
+                (0, "nep171", "1.2.0".to_string(), true),
+            ]
+        );
+        assert_eq!(
+            collect_malformed_events(EventVersionMode::Strict),
+            vec![
+                (
+                    1,
+                    "Unsupported nep141 event version 2.0.0, expected >=1.0.0, <2.0.0".to_string()
+                ),
+                (
+                    1,
+                    "Unsupported nep171 event version 2.0.0, expected >=1.0.0, <2.0.0".to_string()
+                ),
+                (
+                    2,
+                    "Invalid nep171 event version latest: unexpected character 'l' while parsing major version number".to_string()
+                ),
+                (
+                    0,
+                    "Unsupported nep245 event version 2.0.0, expected >=1.0.0, <2.0.0".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_unsupported_versions_in_lenient_mode() {
+        assert_eq!(
+            collect_events(EventVersionMode::Lenient),
+            vec![
+                (0, "nep141", "1.0.0".to_string(), true),
+                (1, "nep141", "2.0.0".to_string(), false),
+                (0, "nep171", "1.2.0".to_string(), true),
+                (1, "nep171", "2.0.0".to_string(), false),
+                (2, "nep171", "latest".to_string(), false),
+                (0, "nep245", "2.0.0".to_string(), false),
+            ]
+        );
+        assert_eq!(collect_malformed_events(EventVersionMode::Lenient), vec![]);
+    }
+}
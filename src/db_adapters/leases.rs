@@ -0,0 +1,191 @@
+use bigdecimal::{BigDecimal, ToPrimitive};
+
+pub(crate) struct Lease {
+    pub range_start: u64,
+    pub range_end: u64,
+    pub last_processed_block_height: Option<u64>,
+}
+
+pub(crate) struct LeasesProgress {
+    pub completed_ranges: i64,
+    pub total_ranges: i64,
+}
+
+// Safe to call from all the workers at the same time, the existing ranges are not touched.
+// Fails if the existing ranges were created with another range size or start,
+// they would overlap with the new ones and the same blocks would be indexed twice
+pub(crate) async fn create_leases(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    start_block_height: u64,
+    end_block_height: u64,
+    range_size: u64,
+) -> anyhow::Result<()> {
+    let mismatched_range: Option<(BigDecimal, BigDecimal)> = sqlx::query_as(
+        "WITH new_ranges AS ( \
+             SELECT range_start, least(range_start + $3 - 1, $2) AS range_end \
+             FROM generate_series($1::numeric, $2::numeric, $3::numeric) AS range_start \
+         ) \
+         SELECT leases.range_start, leases.range_end \
+         FROM block_range_leases leases \
+         JOIN new_ranges ON leases.range_start <= new_ranges.range_end \
+             AND new_ranges.range_start <= leases.range_end \
+         WHERE leases.range_start <> new_ranges.range_start \
+             OR leases.range_end <> new_ranges.range_end \
+         ORDER BY leases.range_start \
+         LIMIT 1",
+    )
+    .bind(BigDecimal::from(start_block_height))
+    .bind(BigDecimal::from(end_block_height))
+    .bind(BigDecimal::from(range_size))
+    .fetch_optional(pool)
+    .await?;
+    if let Some((range_start, range_end)) = mismatched_range {
+        anyhow::bail!(
+            "Range {}..={} in block_range_leases does not match the ranges of size {} starting at {}, \
+             use the same range_size and start_block_height as the previous runs",
+            range_start,
+            range_end,
+            range_size,
+            start_block_height
+        );
+    }
+
+    sqlx::query(
+        "INSERT INTO block_range_leases (range_start, range_end) \
+         SELECT range_start, least(range_start + $3 - 1, $2) \
+         FROM generate_series($1::numeric, $2::numeric, $3::numeric) AS range_start \
+         ON CONFLICT DO NOTHING",
+    )
+    .bind(BigDecimal::from(start_block_height))
+    .bind(BigDecimal::from(end_block_height))
+    .bind(BigDecimal::from(range_size))
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Takes the first range which was never claimed or was abandoned by crashed worker
+pub(crate) async fn claim_lease(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    worker_id: &str,
+    start_block_height: u64,
+    end_block_height: u64,
+    lease_timeout: std::time::Duration,
+) -> anyhow::Result<Option<Lease>> {
+    let lease: Option<(BigDecimal, BigDecimal, Option<BigDecimal>)> = sqlx::query_as(
+        "UPDATE block_range_leases \
+         SET worker_id = $1, leased_until = now() + $2 * interval '1 second' \
+         WHERE range_start = ( \
+             SELECT range_start FROM block_range_leases \
+             WHERE completed_at IS NULL \
+                 AND (leased_until IS NULL OR leased_until < now()) \
+                 AND range_start >= $3 AND range_end <= $4 \
+             ORDER BY range_start \
+             LIMIT 1 \
+             FOR UPDATE SKIP LOCKED \
+         ) \
+         RETURNING range_start, range_end, last_processed_block_height",
+    )
+    .bind(worker_id)
+    .bind(lease_timeout.as_secs_f64())
+    .bind(BigDecimal::from(start_block_height))
+    .bind(BigDecimal::from(end_block_height))
+    .fetch_optional(pool)
+    .await?;
+
+    lease
+        .map(|(range_start, range_end, last_processed_block_height)| {
+            Ok(Lease {
+                range_start: to_block_height(&range_start)?,
+                range_end: to_block_height(&range_end)?,
+                last_processed_block_height: last_processed_block_height
+                    .as_ref()
+                    .map(to_block_height)
+                    .transpose()?,
+            })
+        })
+        .transpose()
+}
+
+// Saves the progress and prolongs the lease at the same time.
+// Fails if the lease was re-claimed by another worker, we should not continue the range then
+pub(crate) async fn store_lease_progress(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    worker_id: &str,
+    range_start: u64,
+    block_height: u64,
+    lease_timeout: std::time::Duration,
+) -> anyhow::Result<()> {
+    let result = sqlx::query(
+        "UPDATE block_range_leases \
+         SET last_processed_block_height = $3, leased_until = now() + $4 * interval '1 second' \
+         WHERE range_start = $1 AND worker_id = $2",
+    )
+    .bind(BigDecimal::from(range_start))
+    .bind(worker_id)
+    .bind(BigDecimal::from(block_height))
+    .bind(lease_timeout.as_secs_f64())
+    .execute(tx)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        anyhow::bail!(
+            "Lease for the range starting at {} was lost by {}",
+            range_start,
+            worker_id
+        );
+    }
+    Ok(())
+}
+
+// Fails the same way as `store_lease_progress` if the lease was re-claimed by another worker
+pub(crate) async fn complete_lease(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    worker_id: &str,
+    range_start: u64,
+) -> anyhow::Result<()> {
+    let result = sqlx::query(
+        "UPDATE block_range_leases SET completed_at = now() \
+         WHERE range_start = $1 AND worker_id = $2 AND completed_at IS NULL",
+    )
+    .bind(BigDecimal::from(range_start))
+    .bind(worker_id)
+    .execute(pool)
+    .await?;
+
+    if result.rows_affected() != 1 {
+        anyhow::bail!(
+            "Lease for the range starting at {} was lost by {}",
+            range_start,
+            worker_id
+        );
+    }
+    Ok(())
+}
+
+pub(crate) async fn get_progress(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    start_block_height: u64,
+    end_block_height: u64,
+) -> anyhow::Result<LeasesProgress> {
+    let (completed_ranges, total_ranges): (i64, i64) = sqlx::query_as(
+        "SELECT count(*) FILTER (WHERE completed_at IS NOT NULL), count(*) \
+         FROM block_range_leases \
+         WHERE range_start >= $1 AND range_end <= $2",
+    )
+    .bind(BigDecimal::from(start_block_height))
+    .bind(BigDecimal::from(end_block_height))
+    .fetch_one(pool)
+    .await?;
+
+    Ok(LeasesProgress {
+        completed_ranges,
+        total_ranges,
+    })
+}
+
+fn to_block_height(value: &BigDecimal) -> anyhow::Result<u64> {
+    value
+        .to_u64()
+        .ok_or_else(|| anyhow::anyhow!("{} is not a valid block height", value))
+}
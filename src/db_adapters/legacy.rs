@@ -0,0 +1,172 @@
+// The common parts of FT and NFT legacy handlers, see `coin::legacy` and `nft::legacy`
+
+use crate::db_adapters;
+use near_lake_framework::near_indexer_primitives;
+use near_primitives::types::AccountId;
+use near_primitives::views::{ActionView, ExecutionStatusView};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+
+/// The contract (or the family of contracts) which needs custom handling of its activity
+#[async_trait::async_trait]
+pub(crate) trait LegacyHandler: Send + Sync {
+    /// `CoinEvent` or `NftEvent`
+    type Row;
+
+    /// Used in the logs
+    fn name(&self) -> &str;
+
+    /// Goes to `event_index` of the produced events, should be unique among all the event types
+    fn event_type_index(&self) -> u8;
+
+    /// The handler is disabled on the chains where it does not match any receiver
+    fn matches_receiver(&self, chain_id: &str, account_id: &AccountId) -> bool;
+
+    /// Fails if the action changes the state in the way the handler can't collect.
+    /// Such successful actions go to `rejected_events` instead of `process_action`,
+    /// so the gap in the history is visible
+    fn check_action_supported(&self, _action: &ActionView) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// The receipts producing NEP-297 events are not passed here.
+    /// `event_index` of the resulting events is initialized later
+    async fn process_action(
+        &self,
+        block_header: &near_indexer_primitives::views::BlockHeaderView,
+        action: &ActionView,
+        outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    ) -> anyhow::Result<Vec<Self::Row>>;
+}
+
+pub(crate) struct LegacyRegistry<R> {
+    handlers: Vec<Box<dyn LegacyHandler<Row = R>>>,
+}
+
+impl<R> Default for LegacyRegistry<R> {
+    fn default() -> Self {
+        Self { handlers: vec![] }
+    }
+}
+
+impl<R> LegacyRegistry<R> {
+    pub fn register(&mut self, handler: Box<dyn LegacyHandler<Row = R>>) -> anyhow::Result<()> {
+        let event_type_index = handler.event_type_index();
+        if db_adapters::STANDARD_EVENTS
+            .iter()
+            .any(|event| event.type_index() == event_type_index)
+        {
+            anyhow::bail!(
+                "Legacy handler {} uses event type index {} reserved for the standard events",
+                handler.name(),
+                event_type_index
+            );
+        }
+        if let Some(registered) = self
+            .handlers
+            .iter()
+            .find(|registered| registered.event_type_index() == event_type_index)
+        {
+            anyhow::bail!(
+                "Legacy handlers {} and {} use the same event type index {}",
+                registered.name(),
+                handler.name(),
+                event_type_index
+            );
+        }
+        self.handlers.push(handler);
+        Ok(())
+    }
+
+    pub fn handlers(&self) -> impl Iterator<Item = &dyn LegacyHandler<Row = R>> {
+        self.handlers.iter().map(|handler| handler.as_ref())
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AccountMatcher {
+    Exact(AccountId),
+    /// Should match the whole account id, don't forget `^` and `$`
+    Pattern(#[serde(deserialize_with = "deserialize_regex")] regex::Regex),
+}
+
+impl AccountMatcher {
+    pub fn is_match(&self, account_id: &AccountId) -> bool {
+        match self {
+            AccountMatcher::Exact(expected_account_id) => expected_account_id == account_id,
+            AccountMatcher::Pattern(pattern) => pattern.is_match(account_id.as_str()),
+        }
+    }
+}
+
+/// The account of the contract on each chain, e.g. `mainnet`, `testnet`.
+/// The contract is not handled on the other chains
+#[derive(Deserialize, Debug)]
+pub(crate) struct ChainAccounts(BTreeMap<String, AccountMatcher>);
+
+impl ChainAccounts {
+    pub fn is_match(&self, chain_id: &str, account_id: &AccountId) -> bool {
+        self.0
+            .get(chain_id)
+            .map_or(false, |account| account.is_match(account_id))
+    }
+}
+
+pub(crate) fn deserialize_regex<'de, D>(deserializer: D) -> Result<regex::Regex, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let pattern = String::deserialize(deserializer)?;
+    regex::Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+// `None` means the arguments of the failed receipt can't be parsed, it's ignored
+pub(crate) fn ignore_failed_receipt<T>(
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    parsed_args: anyhow::Result<T>,
+) -> anyhow::Result<Option<T>> {
+    match parsed_args {
+        Ok(x) => Ok(Some(x)),
+        Err(err) => {
+            match outcome.execution_outcome.outcome.status {
+                // We couldn't parse args for failed receipt. Let's just ignore it, we can't save it properly
+                ExecutionStatusView::Unknown | ExecutionStatusView::Failure(_) => Ok(None),
+                ExecutionStatusView::SuccessValue(_) | ExecutionStatusView::SuccessReceiptId(_) => {
+                    Err(err)
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn get_field<T: DeserializeOwned>(
+    args: &serde_json::Value,
+    field: &str,
+) -> anyhow::Result<T> {
+    let value = args
+        .get(field)
+        .ok_or_else(|| anyhow::anyhow!("missing field `{}`", field))?;
+    serde_json::from_value(value.clone())
+        .map_err(|err| anyhow::anyhow!("invalid field `{}`: {}", field, err))
+}
+
+// The optional fields could be missing or `null`
+pub(crate) fn get_optional_field<T: DeserializeOwned>(
+    args: &serde_json::Value,
+    field: &Option<String>,
+) -> anyhow::Result<Option<T>> {
+    match field {
+        Some(field) if args.get(field).is_some() => get_field::<Option<T>>(args, field),
+        _ => Ok(None),
+    }
+}
+
+pub(crate) fn get_memo(
+    args: &serde_json::Value,
+    memo_field: &Option<String>,
+) -> anyhow::Result<Option<String>> {
+    let memo = get_optional_field::<String>(args, memo_field)?;
+    Ok(memo.map(|s| s.escape_default().to_string()))
+}
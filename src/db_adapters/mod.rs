@@ -1,28 +1,63 @@
 use crate::db_adapters::coin::{FT, FT_LEGACY};
-use crate::db_adapters::nft::NFT;
+use crate::db_adapters::mt::MT;
+use crate::db_adapters::nft::{NFT, NFT_LEGACY};
+use crate::db_adapters::raw_events::RAW;
 use bigdecimal::BigDecimal;
 use near_lake_framework::near_indexer_primitives;
 use near_lake_framework::near_indexer_primitives::views::ExecutionStatusView;
 use std::str::FromStr;
 
-mod coin;
+pub(crate) mod checkpoints;
+pub(crate) mod coin;
+mod contracts;
 mod event_types;
 pub(crate) mod events;
-mod nft;
+pub(crate) mod leases;
+mod legacy;
+mod mt;
+pub(crate) mod nft;
 mod numeric_types;
+mod raw_events;
+mod rejected_events;
+#[cfg(test)]
+pub(crate) mod test_utils;
 
 pub(crate) const CHUNK_SIZE_FOR_BATCH_INSERT: usize = 100;
 pub(crate) const RETRY_COUNT: usize = 10;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum Event {
     Nep141,
     Nep171,
-    Aurora,
-    RainbowBridge,
-    Skyward,
-    TknNear,
-    Wentokensir,
-    WrapNear,
+    Nep171MetadataUpdate,
+    Nep245,
+    Raw,
+    /// The events of legacy FT contracts, the type index is declared by their handler
+    FtLegacy(u8),
+    /// The events of legacy NFT contracts, the type index is declared by their handler
+    NftLegacy(u8),
+}
+
+// Legacy handlers should not reuse the type indices of these events
+pub(crate) const STANDARD_EVENTS: [Event; 5] = [
+    Event::Nep141,
+    Event::Nep171,
+    Event::Nep171MetadataUpdate,
+    Event::Nep245,
+    Event::Raw,
+];
+
+impl Event {
+    pub fn type_index(&self) -> u8 {
+        match self {
+            Event::Nep141 => 1,
+            Event::Nep171 => 2,
+            Event::Nep245 => 9,
+            Event::Nep171MetadataUpdate => 10,
+            Event::Raw => 11,
+            Event::FtLegacy(type_index) | Event::NftLegacy(type_index) => *type_index,
+        }
+    }
 }
 
 pub(crate) struct EventBase {
@@ -53,12 +88,11 @@ fn get_standard(event_type: &Event) -> String {
     match event_type {
         Event::Nep141 => FT,
         Event::Nep171 => NFT,
-        Event::Aurora => FT_LEGACY,
-        Event::RainbowBridge => FT_LEGACY,
-        Event::Skyward => FT_LEGACY,
-        Event::TknNear => FT_LEGACY,
-        Event::Wentokensir => FT_LEGACY,
-        Event::WrapNear => FT_LEGACY,
+        Event::Nep171MetadataUpdate => NFT,
+        Event::Nep245 => MT,
+        Event::Raw => RAW,
+        Event::FtLegacy(_) => FT_LEGACY,
+        Event::NftLegacy(_) => NFT_LEGACY,
     }
     .to_string()
 }
@@ -85,16 +119,7 @@ fn compose_db_index(
     event: &Event,
     event_index: usize,
 ) -> anyhow::Result<BigDecimal> {
-    let event_type_index: u128 = match event {
-        Event::Nep141 => 1,
-        Event::Nep171 => 2,
-        Event::Aurora => 3,
-        Event::RainbowBridge => 4,
-        Event::Skyward => 5,
-        Event::TknNear => 6,
-        Event::Wentokensir => 7,
-        Event::WrapNear => 8,
-    };
+    let event_type_index = event.type_index() as u128;
     let db_index: u128 = (block_timestamp as u128) * 100_000_000 * 100_000_000
         + (*shard_id as u128) * 1_000_000_000
         + event_type_index * 1_000_000
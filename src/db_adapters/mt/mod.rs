@@ -0,0 +1,48 @@
+use crate::configs::{EventVersionMode, InsertMethod};
+use crate::db_adapters::Event;
+use crate::models;
+use crate::models::mt_events::MtEvent;
+use crate::models::rejected_events::RejectedEvent;
+use futures::future::try_join_all;
+use near_lake_framework::near_indexer_primitives;
+
+mod nep245_events;
+
+pub const MT: &str = "MT_NEP245";
+
+pub(crate) async fn store_mt(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    streamer_message: &near_indexer_primitives::StreamerMessage,
+    insert_method: InsertMethod,
+    event_version_mode: EventVersionMode,
+) -> anyhow::Result<(Vec<MtEvent>, Vec<RejectedEvent>)> {
+    let mut nep245_events: Vec<MtEvent> = vec![];
+    let mut rejected_events: Vec<RejectedEvent> = vec![];
+    let mt_events_futures = streamer_message.shards.iter().map(|shard| {
+        nep245_events::collect_nep245_events(
+            &shard.shard_id,
+            &shard.receipt_execution_outcomes,
+            &streamer_message.block.header,
+            event_version_mode,
+        )
+    });
+    for (events, rejected_events_by_shard) in try_join_all(mt_events_futures).await? {
+        nep245_events.extend(events);
+        rejected_events.extend(rejected_events_by_shard);
+    }
+    models::insert(tx, &nep245_events, insert_method).await?;
+    Ok((nep245_events, rejected_events))
+}
+
+pub(crate) fn enumerate_events(
+    mt_events: &mut [MtEvent],
+    shard_id: &near_indexer_primitives::types::ShardId,
+    timestamp: u64,
+    event_type: &Event,
+) -> anyhow::Result<()> {
+    for (index, event) in mt_events.iter_mut().enumerate() {
+        event.event_index =
+            crate::db_adapters::compose_db_index(timestamp, shard_id, event_type, index)?;
+    }
+    Ok(())
+}
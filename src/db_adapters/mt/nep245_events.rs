@@ -0,0 +1,268 @@
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+
+use crate::configs::EventVersionMode;
+use crate::db_adapters::event_types::Nep245Event;
+use crate::db_adapters::rejected_events::build_rejected_event;
+use crate::db_adapters::Event;
+use crate::db_adapters::{events, get_status, mt};
+use crate::models::mt_events::MtEvent;
+use crate::models::rejected_events::RejectedEvent;
+use near_lake_framework::near_indexer_primitives;
+use num_traits::Zero;
+
+use crate::db_adapters::event_types;
+use crate::db_adapters::mt::MT;
+
+pub(crate) async fn collect_nep245_events(
+    shard_id: &near_indexer_primitives::types::ShardId,
+    receipt_execution_outcomes: &[near_indexer_primitives::IndexerExecutionOutcomeWithReceipt],
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    event_version_mode: EventVersionMode,
+) -> anyhow::Result<(Vec<MtEvent>, Vec<RejectedEvent>)> {
+    let mut res = Vec::new();
+    let mut rejected_events = Vec::new();
+    for outcome in receipt_execution_outcomes {
+        for indexed_event in events::extract_events_with_index(outcome, event_version_mode) {
+            if let event_types::NearEvent::Nep245(mt_events) = &indexed_event.event {
+                match compose_mt_db_events(mt_events, outcome, block_header) {
+                    Ok(mut mt_db_events) => {
+                        for mt_event in &mut mt_db_events {
+                            mt_event.event_version = Some(mt_events.version.clone());
+                            mt_event.unsupported_event_version =
+                                !indexed_event.is_version_supported;
+                        }
+                        res.extend(mt_db_events);
+                    }
+                    Err(err) => rejected_events.push(build_rejected_event(
+                        outcome,
+                        block_header,
+                        indexed_event.log_index,
+                        &err,
+                    )?),
+                }
+            }
+        }
+    }
+
+    mt::enumerate_events(&mut res, shard_id, block_header.timestamp, &Event::Nep245)?;
+    Ok((res, rejected_events))
+}
+
+/// The part of the row which is common for all the tokens of one event
+struct MtEventBase<'a> {
+    cause: &'static str,
+    old_owner_id: Option<&'a String>,
+    new_owner_id: Option<&'a String>,
+    authorized_id: Option<&'a String>,
+    token_ids: &'a [String],
+    amounts: &'a [String],
+    memo: Option<&'a String>,
+}
+
+fn compose_mt_db_events(
+    events: &Nep245Event,
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+) -> anyhow::Result<Vec<MtEvent>> {
+    let bases: Vec<MtEventBase> = match &events.event_kind {
+        event_types::Nep245EventKind::MtMint(mint_events) => mint_events
+            .iter()
+            .map(|mint_event| MtEventBase {
+                cause: "MINT",
+                old_owner_id: None,
+                new_owner_id: Some(&mint_event.owner_id),
+                authorized_id: None,
+                token_ids: &mint_event.token_ids,
+                amounts: &mint_event.amounts,
+                memo: mint_event.memo.as_ref(),
+            })
+            .collect(),
+        event_types::Nep245EventKind::MtTransfer(transfer_events) => transfer_events
+            .iter()
+            .map(|transfer_event| MtEventBase {
+                cause: "TRANSFER",
+                old_owner_id: Some(&transfer_event.old_owner_id),
+                new_owner_id: Some(&transfer_event.new_owner_id),
+                authorized_id: transfer_event.authorized_id.as_ref(),
+                token_ids: &transfer_event.token_ids,
+                amounts: &transfer_event.amounts,
+                memo: transfer_event.memo.as_ref(),
+            })
+            .collect(),
+        event_types::Nep245EventKind::MtBurn(burn_events) => burn_events
+            .iter()
+            .map(|burn_event| MtEventBase {
+                cause: "BURN",
+                old_owner_id: Some(&burn_event.owner_id),
+                new_owner_id: None,
+                authorized_id: burn_event.authorized_id.as_ref(),
+                token_ids: &burn_event.token_ids,
+                amounts: &burn_event.amounts,
+                memo: burn_event.memo.as_ref(),
+            })
+            .collect(),
+    };
+
+    let mut mt_events = vec![];
+    for base in bases {
+        if base.token_ids.len() != base.amounts.len() {
+            anyhow::bail!(
+                "{} token_ids and {} amounts in {} event",
+                base.token_ids.len(),
+                base.amounts.len(),
+                base.cause
+            );
+        }
+        for (token_id, amount) in base.token_ids.iter().zip(base.amounts) {
+            mt_events.push(MtEvent {
+                event_index: BigDecimal::zero(), // initialized later
+                standard: MT.to_string(),
+                receipt_id: outcome.receipt.receipt_id.to_string(),
+                block_height: BigDecimal::from(block_header.height),
+                block_timestamp: BigDecimal::from(block_header.timestamp),
+                contract_account_id: outcome.receipt.receiver_id.to_string(),
+                token_id: token_id.escape_default().to_string(),
+                cause: base.cause.to_string(),
+                status: get_status(&outcome.execution_outcome.outcome.status),
+                old_owner_account_id: base.old_owner_id.map(|s| s.escape_default().to_string()),
+                new_owner_account_id: base.new_owner_id.map(|s| s.escape_default().to_string()),
+                authorized_account_id: base.authorized_id.map(|s| s.escape_default().to_string()),
+                amount: BigDecimal::from_str(amount)
+                    .map_err(|err| anyhow::anyhow!("Invalid amount {}: {}", amount, err))?,
+                event_memo: base.memo.map(|s| s.escape_default().to_string()),
+                event_version: None, // initialized later
+                unsupported_event_version: false,
+            });
+        }
+    }
+    Ok(mt_events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_adapters::test_utils::collect_fixture;
+    use futures::FutureExt;
+
+    async fn collect_fixture_events() -> (Vec<MtEvent>, Vec<RejectedEvent>) {
+        collect_fixture("nep245_events", |shard_id, outcomes, block_header| {
+            collect_nep245_events(shard_id, outcomes, block_header, EventVersionMode::Strict)
+                .boxed_local()
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn enumerates_events_of_the_block() {
+        let (events, _) = collect_fixture_events().await;
+        let event_indexes: Vec<_> = events
+            .iter()
+            .map(|event| event.event_index.to_string())
+            .collect();
+        assert_eq!(
+            event_indexes,
+            (0..4)
+                .map(|index| format!("1735000002000000000000000000900000{}", index))
+                .collect::<Vec<_>>()
+        );
+        for event in &events {
+            assert_eq!(event.block_height, BigDecimal::from(75000002));
+            assert_eq!(
+                event.block_timestamp,
+                BigDecimal::from(1735000002000000000u64)
+            );
+            assert_eq!(event.standard, MT);
+        }
+    }
+
+    #[tokio::test]
+    async fn pairs_token_ids_with_amounts() {
+        let (events, _) = collect_fixture_events().await;
+        let rows: Vec<_> = events
+            .iter()
+            .filter(|event| event.cause != "TRANSFER")
+            .map(|event| {
+                (
+                    event.cause.as_str(),
+                    event.token_id.as_str(),
+                    event.amount.to_string(),
+                    event.event_memo.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("MINT", "gold", "100".to_string(), Some("airdrop")),
+                ("MINT", "silver", "2500".to_string(), Some("airdrop")),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_authorized_account_of_transfer() {
+        let (events, _) = collect_fixture_events().await;
+        let rows: Vec<_> = events
+            .iter()
+            .filter(|event| event.receipt_id == "GNkzALjWboXYRsaniMKDGAfGvvhUjpEk9iTu5Vpg2KeA")
+            .map(|event| {
+                (
+                    event.old_owner_account_id.as_deref(),
+                    event.new_owner_account_id.as_deref(),
+                    event.authorized_account_id.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![(Some("alice.near"), Some("bob.near"), Some("market.near"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_events_of_failed_receipts() {
+        let (events, _) = collect_fixture_events().await;
+        let failed_receipts: Vec<_> = events
+            .iter()
+            .filter(|event| event.status == "FAILURE")
+            .map(|event| event.receipt_id.as_str())
+            .collect();
+        assert_eq!(
+            failed_receipts,
+            vec!["5cVPKScEUDGqPHwQ3r7uQEvTXXJTTzqy9TdKX8rGSdio"]
+        );
+    }
+
+    // The first burn of the log has more token_ids than amounts, the whole log is rejected
+    #[tokio::test]
+    async fn rejects_events_with_invalid_amounts() {
+        let (_, rejected_events) = collect_fixture_events().await;
+        let errors: Vec<_> = rejected_events
+            .iter()
+            .map(|event| {
+                (
+                    event.receipt_id.as_str(),
+                    event.log_index,
+                    event.error.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "EsQrz4gDbtSWGVJqriZ59nmQmhD22sk7TE3Gf132fB4S",
+                    0,
+                    "2 token_ids and 1 amounts in BURN event",
+                ),
+                (
+                    "2jWBxDeom593grqNHtjbHuvi3mgvXEGMNrQjyBj8tbUB",
+                    1,
+                    "Invalid amount ten: invalid digit found in string",
+                ),
+            ]
+        );
+    }
+}
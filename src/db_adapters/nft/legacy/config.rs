@@ -0,0 +1,71 @@
+use crate::db_adapters::legacy::ChainAccounts;
+use serde::Deserialize;
+
+/// The description of all the legacy NFT contracts, see DOC.md for the format
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LegacyConfig {
+    pub contracts: Vec<LegacyContract>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LegacyContract {
+    /// Used in the logs
+    pub name: String,
+    /// Goes to `event_index` of the events, see `LegacyHandler::event_type_index`
+    pub event_type_index: u8,
+    pub accounts: ChainAccounts,
+    /// The methods which do not move the tokens
+    #[serde(default)]
+    pub ignored_methods: Vec<String>,
+    /// The methods which move the tokens, but we can't collect them from the call.
+    /// Their successful calls go to `rejected_events`
+    #[serde(default)]
+    pub unsupported_methods: Vec<String>,
+    pub rules: Vec<MethodRule>,
+}
+
+// `deny_unknown_fields` does not work together with `flatten`
+#[derive(Deserialize, Debug)]
+pub(crate) struct MethodRule {
+    pub methods: Vec<String>,
+    #[serde(flatten)]
+    pub action: MethodAction,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub(crate) enum MethodAction {
+    /// MINT of one token.
+    /// The owner is the predecessor if `owner_id_field` is not set,
+    /// the token id is the returned value if `token_id_field` is not set
+    Mint {
+        owner_id_field: Option<String>,
+        token_id_field: Option<String>,
+        memo_field: Option<String>,
+    },
+    /// TRANSFER by the predecessor, it could be the owner or the approved account.
+    /// The owner is taken from the previous events of the block or from `nft_token` of the previous block
+    Transfer {
+        receiver_id_field: String,
+        token_id_field: String,
+        memo_field: Option<String>,
+    },
+    /// `nft_resolve_transfer` of the standard implementation.
+    /// The token is sent back to the previous owner if `false` is returned
+    ResolveTransfer {
+        previous_owner_id_field: String,
+        receiver_id_field: String,
+        token_id_field: String,
+    },
+    /// BURN by the predecessor
+    Burn {
+        token_id_field: String,
+        memo_field: Option<String>,
+    },
+}
+
+pub(crate) fn parse_config(config: &str) -> anyhow::Result<LegacyConfig> {
+    Ok(toml::from_str(config)?)
+}
@@ -0,0 +1,769 @@
+use crate::db_adapters;
+use crate::db_adapters::legacy::{
+    get_field, get_memo, get_optional_field, ignore_failed_receipt, LegacyHandler, LegacyRegistry,
+};
+use crate::db_adapters::rejected_events::build_rejected_action;
+use crate::db_adapters::{nft, Event};
+use crate::models::nft_events::NftEvent;
+use crate::models::rejected_events::RejectedEvent;
+use anyhow::Context;
+use bigdecimal::BigDecimal;
+use config::{LegacyContract, MethodAction};
+use near_jsonrpc_client::{methods, JsonRpcClient};
+use near_lake_framework::near_indexer_primitives;
+use near_primitives::types::AccountId;
+use near_primitives::views::{ActionView, ExecutionStatusView, ReceiptEnumView};
+use num_traits::Zero;
+use once_cell::sync::OnceCell;
+use std::collections::HashMap;
+use std::path::Path;
+
+mod config;
+
+static LEGACY_HANDLERS: OnceCell<LegacyRegistry<NftEvent>> = OnceCell::new();
+
+/// Reads the legacy NFT contracts from the given config, `contracts.toml` embedded to the binary is used otherwise.
+/// Should be called on startup, before any block is handled
+pub(crate) fn init_legacy_handlers(config_path: Option<&Path>) -> anyhow::Result<()> {
+    let registry = build_registry(config_path)?;
+    LEGACY_HANDLERS
+        .set(registry)
+        .map_err(|_| anyhow::anyhow!("Legacy NFT handlers are already initialized"))
+}
+
+fn legacy_handlers() -> anyhow::Result<&'static LegacyRegistry<NftEvent>> {
+    LEGACY_HANDLERS.get_or_try_init(|| build_registry(None))
+}
+
+// The contracts from the config are handled by one engine,
+// the handlers implemented in code should be registered here as well
+fn build_registry(config_path: Option<&Path>) -> anyhow::Result<LegacyRegistry<NftEvent>> {
+    let config = match config_path {
+        Some(path) => {
+            let config = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            config::parse_config(&config).with_context(|| {
+                format!("Invalid legacy NFT contracts config {}", path.display())
+            })?
+        }
+        None => config::parse_config(include_str!("contracts.toml"))?,
+    };
+    let mut registry = LegacyRegistry::default();
+    for contract in config.contracts {
+        registry.register(Box::new(contract))?;
+    }
+    Ok(registry)
+}
+
+pub(crate) async fn collect_legacy(
+    shard_id: &near_indexer_primitives::types::ShardId,
+    receipt_execution_outcomes: &[near_indexer_primitives::IndexerExecutionOutcomeWithReceipt],
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    chain_id: &str,
+    rpc_client: &JsonRpcClient,
+) -> anyhow::Result<(Vec<NftEvent>, Vec<RejectedEvent>)> {
+    let mut events: Vec<NftEvent> = vec![];
+    let mut rejected_events: Vec<RejectedEvent> = vec![];
+    for handler in legacy_handlers()?.handlers() {
+        let (handler_events, handler_rejected_events) = collect_handler(
+            handler,
+            shard_id,
+            receipt_execution_outcomes,
+            block_header,
+            chain_id,
+            rpc_client,
+        )
+        .await?;
+        events.extend(handler_events);
+        rejected_events.extend(handler_rejected_events);
+    }
+    Ok((events, rejected_events))
+}
+
+// The handlers return the raw token ids, they are escaped here the same way as in NEP-171 events.
+// TRANSFER without the old owner is completed here, see `resolve_old_owners`
+async fn collect_handler(
+    handler: &dyn LegacyHandler<Row = NftEvent>,
+    shard_id: &near_indexer_primitives::types::ShardId,
+    receipt_execution_outcomes: &[near_indexer_primitives::IndexerExecutionOutcomeWithReceipt],
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    chain_id: &str,
+    rpc_client: &JsonRpcClient,
+) -> anyhow::Result<(Vec<NftEvent>, Vec<RejectedEvent>)> {
+    let mut events: Vec<NftEvent> = vec![];
+    let mut rejected_events: Vec<RejectedEvent> = vec![];
+    let mut owners = TokenOwners::new();
+
+    for outcome in receipt_execution_outcomes {
+        if !handler.matches_receiver(chain_id, &outcome.receipt.receiver_id)
+            || !db_adapters::events::extract_events(outcome).is_empty()
+        {
+            continue;
+        }
+        let is_successful =
+            db_adapters::get_status(&outcome.execution_outcome.outcome.status) == "SUCCESS";
+        if let ReceiptEnumView::Action { actions, .. } = &outcome.receipt.receipt {
+            for (action_index, action) in actions.iter().enumerate() {
+                let resolved_events = match handler.check_action_supported(action) {
+                    Ok(()) => {
+                        let action_events = handler
+                            .process_action(block_header, action, outcome)
+                            .await?;
+                        resolve_old_owners(rpc_client, block_header, &mut owners, action_events)
+                            .await?
+                    }
+                    // The failed action did not move anything, so nothing is lost
+                    Err(_) if !is_successful => continue,
+                    Err(err) => Err(err),
+                };
+                match resolved_events {
+                    Ok(resolved_events) => events.extend(resolved_events),
+                    Err(err) => rejected_events.push(build_rejected_action(
+                        outcome,
+                        block_header,
+                        action_index,
+                        action,
+                        &err,
+                    )?),
+                }
+            }
+        }
+    }
+    for event in &mut events {
+        event.token_id = event.token_id.escape_default().to_string();
+    }
+    nft::enumerate_events(
+        &mut events,
+        shard_id,
+        block_header.timestamp,
+        &Event::NftLegacy(handler.event_type_index()),
+    )?;
+
+    Ok((events, rejected_events))
+}
+
+/// The owners of the tokens after the previous actions of the block, `None` for the burnt tokens.
+/// The key is the contract and the token id
+type TokenOwners = HashMap<(String, String), Option<String>>;
+
+// The legacy transfer does not tell whose token it moves, the predecessor could be the owner or the approved account.
+// The owner is taken from the previous actions of the block, otherwise from `nft_token` at the end of the previous block.
+// The inner error rejects the action: the successful transfer of the token we don't know
+async fn resolve_old_owners(
+    rpc_client: &JsonRpcClient,
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    owners: &mut TokenOwners,
+    events: Vec<NftEvent>,
+) -> anyhow::Result<anyhow::Result<Vec<NftEvent>>> {
+    let mut resolved_events = Vec::with_capacity(events.len());
+    for mut event in events {
+        let token = (event.contract_account_id.clone(), event.token_id.clone());
+        let is_successful = event.status == "SUCCESS";
+        if event.cause == "TRANSFER" && event.old_owner_account_id.is_none() {
+            let old_owner_id = match owners.get(&token) {
+                Some(owner_id) => owner_id.clone(),
+                None => {
+                    get_nft_owner_or_retry(rpc_client, &token.0, &token.1, &block_header.prev_hash)
+                        .await?
+                }
+            };
+            match old_owner_id {
+                Some(old_owner_id) => {
+                    if event.authorized_account_id.as_ref() == Some(&old_owner_id) {
+                        event.authorized_account_id = None;
+                    }
+                    event.old_owner_account_id = Some(old_owner_id);
+                }
+                // The token does not exist, the failed transfer moved nothing
+                None if !is_successful => continue,
+                None => {
+                    return Ok(Err(anyhow::anyhow!(
+                        "The owner of token {} is unknown",
+                        token.1
+                    )))
+                }
+            }
+        }
+        // The failed receipts do not move the tokens
+        if is_successful {
+            owners.insert(token, event.new_owner_account_id.clone());
+        }
+        resolved_events.push(event);
+    }
+    Ok(Ok(resolved_events))
+}
+
+async fn get_nft_owner_or_retry(
+    rpc_client: &JsonRpcClient,
+    contract_account_id: &str,
+    token_id: &str,
+    block_hash: &near_indexer_primitives::CryptoHash,
+) -> anyhow::Result<Option<String>> {
+    let mut interval = crate::INTERVAL;
+    let mut retry_attempt = 0usize;
+
+    loop {
+        retry_attempt += 1;
+        match get_nft_owner(rpc_client, contract_account_id, token_id, block_hash).await {
+            Ok(owner_id) => return Ok(owner_id),
+            Err(err) if retry_attempt < crate::db_adapters::RETRY_COUNT => {
+                tracing::warn!(
+                    target: crate::LOGGING_PREFIX,
+                    "{:#}\n Retrying in {} milliseconds...",
+                    err,
+                    interval.as_millis(),
+                );
+                tokio::time::sleep(interval).await;
+                if interval < crate::MAX_DELAY_TIME {
+                    interval *= 2;
+                }
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// The owner of the token at the end of the block.
+/// `None` means the token or the contract does not exist, or the contract can't answer `nft_token`
+async fn get_nft_owner(
+    rpc_client: &JsonRpcClient,
+    contract_account_id: &str,
+    token_id: &str,
+    block_hash: &near_indexer_primitives::CryptoHash,
+) -> anyhow::Result<Option<String>> {
+    // The client is built on another version of near-primitives, so we talk to it in plain JSON
+    let request = methods::any::<Result<serde_json::Value, serde_json::Value>>(
+        "query",
+        serde_json::json!({
+            "request_type": "call_function",
+            "block_id": block_hash.to_string(),
+            "account_id": contract_account_id,
+            "method_name": "nft_token",
+            "args_base64": base64::encode(
+                &serde_json::json!({ "token_id": token_id }).to_string()
+            ),
+        }),
+    );
+
+    let response = match rpc_client.call(request).await {
+        Ok(response) => response,
+        Err(err) => {
+            return match err.handler_error().and_then(|error| error["name"].as_str()) {
+                Some("UNKNOWN_ACCOUNT" | "NO_CONTRACT_CODE" | "CONTRACT_EXECUTION_ERROR") => {
+                    Ok(None)
+                }
+                _ => Err(anyhow::anyhow!(
+                    "Failed to query nft_token({}) in {} at block {}: {}",
+                    token_id,
+                    contract_account_id,
+                    block_hash,
+                    err
+                )),
+            }
+        }
+    };
+    // The contract execution errors are still returned as a successful response with `error` field
+    if response.get("error").is_some() {
+        return Ok(None);
+    }
+    Ok(
+        serde_json::from_value::<Vec<u8>>(response["result"].clone())
+            .ok()
+            .and_then(|result| serde_json::from_slice::<serde_json::Value>(&result).ok())
+            .and_then(|token| token["owner_id"].as_str().map(str::to_string)),
+    )
+}
+
+#[async_trait::async_trait]
+impl LegacyHandler for LegacyContract {
+    type Row = NftEvent;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn event_type_index(&self) -> u8 {
+        self.event_type_index
+    }
+
+    fn matches_receiver(&self, chain_id: &str, account_id: &AccountId) -> bool {
+        self.accounts.is_match(chain_id, account_id)
+    }
+
+    fn check_action_supported(&self, action: &ActionView) -> anyhow::Result<()> {
+        match action {
+            ActionView::FunctionCall { method_name, .. }
+                if self.unsupported_methods.contains(method_name) =>
+            {
+                anyhow::bail!("{} of {} is not supported", method_name, self.name)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn process_action(
+        &self,
+        block_header: &near_indexer_primitives::views::BlockHeaderView,
+        action: &ActionView,
+        outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    ) -> anyhow::Result<Vec<NftEvent>> {
+        process_function(self, block_header, action, outcome)
+    }
+}
+
+fn process_function(
+    contract: &LegacyContract,
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    action: &ActionView,
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+) -> anyhow::Result<Vec<NftEvent>> {
+    let (method_name, args) = match action {
+        ActionView::FunctionCall {
+            method_name, args, ..
+        } => (method_name, args),
+        _ => return Ok(vec![]),
+    };
+
+    let decoded_args = base64::decode(args)?;
+
+    if contract.ignored_methods.contains(method_name) {
+        return Ok(vec![]);
+    }
+    let rule = match contract
+        .rules
+        .iter()
+        .find(|rule| rule.methods.contains(method_name))
+    {
+        Some(rule) => rule,
+        None => {
+            tracing::error!(
+                target: crate::LOGGING_PREFIX,
+                "{} {} new method found: {}, receipt {}",
+                contract.name,
+                block_header.height,
+                method_name,
+                outcome.receipt.receipt_id
+            );
+            return Ok(vec![]);
+        }
+    };
+    let event_builder = EventBuilder {
+        event: Event::NftLegacy(contract.event_type_index),
+        block_header,
+        outcome,
+    };
+    let predecessor_id = outcome.receipt.predecessor_id.clone();
+
+    match &rule.action {
+        MethodAction::Mint {
+            owner_id_field,
+            token_id_field,
+            memo_field,
+        } => {
+            let (owner_id, token_id, memo) = match parse_args(outcome, &decoded_args, |args| {
+                Ok((
+                    get_optional_field::<AccountId>(args, owner_id_field)?,
+                    get_optional_field::<String>(args, token_id_field)?,
+                    get_memo(args, memo_field)?,
+                ))
+            })? {
+                Some(x) => x,
+                None => return Ok(vec![]),
+            };
+            let token_id = match (token_id, &outcome.execution_outcome.outcome.status) {
+                (Some(token_id), _) => token_id,
+                (None, ExecutionStatusView::SuccessValue(value)) => {
+                    serde_json::from_slice::<String>(&base64::decode(value)?)?
+                }
+                // The failed receipt does not return the id of the token it tried to mint
+                (None, _) => return Ok(vec![]),
+            };
+            Ok(vec![event_builder.build(
+                &token_id,
+                "MINT",
+                None,
+                Some(owner_id.unwrap_or(predecessor_id)),
+                None,
+                memo,
+            )?])
+        }
+
+        // The owner is resolved in `collect_handler`, the predecessor is kept as the authorized account till then
+        MethodAction::Transfer {
+            receiver_id_field,
+            token_id_field,
+            memo_field,
+        } => {
+            let (receiver_id, token_id, memo) = match parse_args(outcome, &decoded_args, |args| {
+                Ok((
+                    get_field::<AccountId>(args, receiver_id_field)?,
+                    get_field::<String>(args, token_id_field)?,
+                    get_memo(args, memo_field)?,
+                ))
+            })? {
+                Some(x) => x,
+                None => return Ok(vec![]),
+            };
+            Ok(vec![event_builder.build(
+                &token_id,
+                "TRANSFER",
+                None,
+                Some(receiver_id),
+                Some(predecessor_id),
+                memo,
+            )?])
+        }
+
+        // If TRANSFER of `nft_transfer_call` failed, it's revoked
+        MethodAction::ResolveTransfer {
+            previous_owner_id_field,
+            receiver_id_field,
+            token_id_field,
+        } => {
+            let is_transferred = match &outcome.execution_outcome.outcome.status {
+                ExecutionStatusView::SuccessValue(value) => {
+                    serde_json::from_slice::<bool>(&base64::decode(value)?)?
+                }
+                _ => return Ok(vec![]),
+            };
+            if is_transferred {
+                return Ok(vec![]);
+            }
+            let (previous_owner_id, receiver_id, token_id) =
+                match parse_args(outcome, &decoded_args, |args| {
+                    Ok((
+                        get_field::<AccountId>(args, previous_owner_id_field)?,
+                        get_field::<AccountId>(args, receiver_id_field)?,
+                        get_field::<String>(args, token_id_field)?,
+                    ))
+                })? {
+                    Some(x) => x,
+                    None => return Ok(vec![]),
+                };
+            Ok(vec![event_builder.build(
+                &token_id,
+                "TRANSFER",
+                Some(receiver_id),
+                Some(previous_owner_id),
+                None,
+                None,
+            )?])
+        }
+
+        MethodAction::Burn {
+            token_id_field,
+            memo_field,
+        } => {
+            let (token_id, memo) = match parse_args(outcome, &decoded_args, |args| {
+                Ok((
+                    get_field::<String>(args, token_id_field)?,
+                    get_memo(args, memo_field)?,
+                ))
+            })? {
+                Some(x) => x,
+                None => return Ok(vec![]),
+            };
+            Ok(vec![event_builder.build(
+                &token_id,
+                "BURN",
+                Some(predecessor_id),
+                None,
+                None,
+                memo,
+            )?])
+        }
+    }
+}
+
+struct EventBuilder<'a> {
+    event: Event,
+    block_header: &'a near_indexer_primitives::views::BlockHeaderView,
+    outcome: &'a near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+}
+
+impl EventBuilder<'_> {
+    fn build(
+        &self,
+        token_id: &str,
+        cause: &str,
+        old_owner_id: Option<AccountId>,
+        new_owner_id: Option<AccountId>,
+        authorized_id: Option<AccountId>,
+        memo: Option<String>,
+    ) -> anyhow::Result<NftEvent> {
+        let base = db_adapters::get_base(self.event, self.outcome, self.block_header)?;
+        Ok(NftEvent {
+            event_index: BigDecimal::zero(), // initialized later
+            standard: base.standard,
+            receipt_id: base.receipt_id,
+            block_height: base.block_height,
+            block_timestamp: base.block_timestamp,
+            contract_account_id: base.contract_account_id.to_string(),
+            token_id: token_id.to_string(), // escaped later
+            cause: cause.to_string(),
+            status: db_adapters::get_status(&base.status),
+            old_owner_account_id: old_owner_id.map(|id| id.to_string()),
+            new_owner_account_id: new_owner_id.map(|id| id.to_string()),
+            authorized_account_id: authorized_id.map(|id| id.to_string()),
+            event_memo: memo,
+            event_version: None,
+            unsupported_event_version: false,
+        })
+    }
+}
+
+// `None` means the arguments of the failed receipt can't be parsed, it's ignored
+fn parse_args<T>(
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    decoded_args: &[u8],
+    parse: impl FnOnce(&serde_json::Value) -> anyhow::Result<T>,
+) -> anyhow::Result<Option<T>> {
+    let args = serde_json::from_slice(decoded_args).map_err(anyhow::Error::from);
+    ignore_failed_receipt(outcome, args.and_then(|args| parse(&args)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_adapters::test_utils::{collect_fixture, start_rpc};
+    use futures::FutureExt;
+
+    // Answers `nft_token` for the tokens which are not minted in the fixture
+    fn respond_nft_token(request: &serde_json::Value) -> serde_json::Value {
+        let args = base64::decode(request["params"]["args_base64"].as_str().unwrap()).unwrap();
+        let args: serde_json::Value = serde_json::from_slice(&args).unwrap();
+        let token = match args["token_id"].as_str().unwrap() {
+            "7" => serde_json::json!({ "token_id": "7", "owner_id": "alice.near" }),
+            "5" => serde_json::json!({ "token_id": "5", "owner_id": "frank.near" }),
+            _ => serde_json::Value::Null,
+        };
+        serde_json::json!({
+            "result": {
+                "result": token.to_string().into_bytes(),
+                "logs": [],
+                "block_height": 75000006,
+                "block_hash": "11111111111111111111111111111111",
+            }
+        })
+    }
+
+    #[test]
+    fn registers_legacy_contracts() {
+        let registry = build_registry(None).unwrap();
+        let names: Vec<_> = registry.handlers().map(|handler| handler.name()).collect();
+        assert_eq!(names, vec!["PARAS", "MINTBASE"]);
+    }
+
+    async fn collect_fixture_events(chain_id: &'static str) -> (Vec<NftEvent>, Vec<RejectedEvent>) {
+        let rpc_client = JsonRpcClient::connect(start_rpc(respond_nft_token));
+        collect_fixture("legacy_nft_events", |shard_id, outcomes, block_header| {
+            async move {
+                collect_legacy(shard_id, outcomes, block_header, chain_id, &rpc_client).await
+            }
+            .boxed_local()
+        })
+        .await
+    }
+
+    fn owners<'a>(
+        events: &'a [NftEvent],
+        contract_account_id: &str,
+        cause: &str,
+    ) -> Vec<(&'a str, Option<&'a str>, Option<&'a str>)> {
+        events
+            .iter()
+            .filter(|event| {
+                event.contract_account_id == contract_account_id && event.cause == cause
+            })
+            .map(|event| {
+                (
+                    event.token_id.as_str(),
+                    event.old_owner_account_id.as_deref(),
+                    event.new_owner_account_id.as_deref(),
+                )
+            })
+            .collect()
+    }
+
+    // `nft_buy` mints to the predecessor, `nft_mint` to the receiver from the arguments,
+    // the token id is the returned value in both cases
+    #[tokio::test]
+    async fn mints_and_burns() {
+        let (events, _) = collect_fixture_events("mainnet").await;
+        assert_eq!(
+            owners(&events, "x.paras.near", "MINT"),
+            vec![
+                ("42:1", None, Some("alice.near")),
+                ("42:2", None, Some("bob.near"))
+            ]
+        );
+        assert_eq!(
+            owners(&events, "x.paras.near", "BURN"),
+            vec![("42:1", Some("carol.near"), None)]
+        );
+    }
+
+    // The owner is taken from the mint of the same block, the predecessor is the approved account then
+    #[tokio::test]
+    async fn transfers_with_and_without_approval() {
+        let (events, _) = collect_fixture_events("mainnet").await;
+        let rows: Vec<_> = events
+            .iter()
+            .filter(|event| {
+                event.contract_account_id == "x.paras.near" && event.cause == "TRANSFER"
+            })
+            .map(|event| {
+                (
+                    event.old_owner_account_id.as_deref(),
+                    event.new_owner_account_id.as_deref(),
+                    event.authorized_account_id.as_deref(),
+                    event.event_memo.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (Some("alice.near"), Some("carol.near"), None, Some("gift")),
+                (
+                    Some("bob.near"),
+                    Some("dave.near"),
+                    Some("marketplace.paras.near"),
+                    None
+                ),
+            ]
+        );
+    }
+
+    // The owner of the token minted before the block is taken from `nft_token`
+    #[tokio::test]
+    async fn resolves_owner_of_previous_blocks() {
+        let (events, _) = collect_fixture_events("mainnet").await;
+        let rows: Vec<_> = events
+            .iter()
+            .filter(|event| event.token_id == "5")
+            .map(|event| {
+                (
+                    event.old_owner_account_id.as_deref(),
+                    event.new_owner_account_id.as_deref(),
+                    event.authorized_account_id.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![(Some("frank.near"), Some("erin.near"), Some("market.near"))]
+        );
+    }
+
+    // The batch mint does not tell the minted ids, the transfer of the unknown token has no owner
+    #[tokio::test]
+    async fn rejects_actions_which_can_not_be_collected() {
+        let (events, rejected_events) = collect_fixture_events("mainnet").await;
+        assert!(events.iter().all(|event| event.token_id != "100"));
+        let rows: Vec<_> = rejected_events
+            .iter()
+            .map(|event| {
+                (
+                    event.receipt_id.as_str(),
+                    event.log_index,
+                    event.error.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "3p3XJEe3NCyv9ggbtRVhEjmCiHRBHgnmeQymM7gMyzu2",
+                    -1,
+                    "nft_batch_mint of MINTBASE is not supported"
+                ),
+                (
+                    "GFNVUExQpf87qjThN8dkXDCkRgPLfPFX7LF93C86kcX1",
+                    -1,
+                    "The owner of token 100 is unknown"
+                ),
+            ]
+        );
+        for event in &rejected_events {
+            assert_eq!(event.contract_account_id, "store.mintbase1.near");
+        }
+    }
+
+    // The first resolve returns `false`, the second one keeps the token at the receiver
+    #[tokio::test]
+    async fn returns_token_when_transfer_call_fails() {
+        let (events, _) = collect_fixture_events("mainnet").await;
+        let rows: Vec<_> = owners(&events, "store.mintbase1.near", "TRANSFER")
+            .into_iter()
+            .filter(|(token_id, _, _)| *token_id == "7")
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("7", Some("alice.near"), Some("market.near")),
+                ("7", Some("market.near"), Some("alice.near"))
+            ]
+        );
+    }
+
+    // NEP-171 events, failed receipts, ignored and unknown methods,
+    // the account which looks similar to the legacy one; the unsupported methods are rejected
+    #[tokio::test]
+    async fn skips_receipts_without_legacy_activity() {
+        let (events, _) = collect_fixture_events("mainnet").await;
+        for receipt_id in [
+            "J3E1C7UJN8LRkYQPjRPv2arwDYAF67pLjURsAMXFyGdq",
+            "8dwWHkDS633k6AM3fyn1UsvqAKjh1zAvwYA92Pdxgimd",
+            "CnNf3HFXHvaQMegEKVmK766AR3RyxicaPtUBVDja12gw",
+            "9mTzrJV2k39RdbVsHCRZgRE2D3AJLzGzrYyq89AtaFe3",
+            "GxvcsAXCtdv9s5jS3DvP8ak9JMAR2cQjpgTM7TbwZ9qK",
+            "3p3XJEe3NCyv9ggbtRVhEjmCiHRBHgnmeQymM7gMyzu2",
+            "7SXAxeLZoY9CDrizKccNEFm3fVtD1vJYG7PmiVwQXJxm",
+        ] {
+            assert!(
+                events.iter().all(|event| event.receipt_id != receipt_id),
+                "{} should be skipped",
+                receipt_id
+            );
+        }
+        assert!(events.iter().all(|event| event.status == "SUCCESS"));
+    }
+
+    #[tokio::test]
+    async fn uses_event_type_of_each_contract() {
+        let (events, _) = collect_fixture_events("mainnet").await;
+        let mut event_types: Vec<_> = events
+            .iter()
+            .map(|event| {
+                let event_index = event.event_index.to_string();
+                (
+                    event.contract_account_id.as_str(),
+                    event_index[event_index.len() - 9..event_index.len() - 6].to_string(),
+                )
+            })
+            .collect();
+        event_types.dedup();
+        assert_eq!(
+            event_types,
+            vec![
+                ("x.paras.near", "012".to_string()),
+                ("store.mintbase1.near", "013".to_string()),
+            ]
+        );
+        for event in &events {
+            assert_eq!(event.block_height, BigDecimal::from(75000007));
+            assert_eq!(event.standard, nft::NFT_LEGACY);
+            assert_eq!(event.event_version, None);
+        }
+    }
+
+    // None of the contracts is configured for testnet
+    #[tokio::test]
+    async fn handles_contracts_of_the_chain() {
+        let (events, rejected_events) = collect_fixture_events("testnet").await;
+        assert!(events.is_empty());
+        assert!(rejected_events.is_empty());
+    }
+}
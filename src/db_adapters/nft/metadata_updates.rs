@@ -0,0 +1,198 @@
+use bigdecimal::BigDecimal;
+
+use crate::configs::EventVersionMode;
+use crate::db_adapters::event_types::Nep171Event;
+use crate::db_adapters::Event;
+use crate::db_adapters::{events, get_status};
+use crate::models::nft_metadata_updates::NftMetadataUpdate;
+use near_lake_framework::near_indexer_primitives;
+use num_traits::Zero;
+
+use crate::db_adapters::event_types;
+use crate::db_adapters::nft::NFT;
+
+pub(crate) async fn collect_nep171_metadata_updates(
+    shard_id: &near_indexer_primitives::types::ShardId,
+    receipt_execution_outcomes: &[near_indexer_primitives::IndexerExecutionOutcomeWithReceipt],
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    event_version_mode: EventVersionMode,
+) -> anyhow::Result<Vec<NftMetadataUpdate>> {
+    let mut res = Vec::new();
+    for outcome in receipt_execution_outcomes {
+        for indexed_event in events::extract_events_with_index(outcome, event_version_mode) {
+            if let event_types::NearEvent::Nep171(nft_events) = &indexed_event.event {
+                let mut metadata_updates =
+                    compose_metadata_update_db_events(nft_events, outcome, block_header);
+                for metadata_update in &mut metadata_updates {
+                    metadata_update.event_version = Some(nft_events.version.clone());
+                    metadata_update.unsupported_event_version = !indexed_event.is_version_supported;
+                }
+                res.extend(metadata_updates);
+            }
+        }
+    }
+
+    for (index, event) in res.iter_mut().enumerate() {
+        event.event_index = crate::db_adapters::compose_db_index(
+            block_header.timestamp,
+            shard_id,
+            &Event::Nep171MetadataUpdate,
+            index,
+        )?;
+    }
+    Ok(res)
+}
+
+fn compose_metadata_update_db_events(
+    events: &Nep171Event,
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+) -> Vec<NftMetadataUpdate> {
+    let build_event = |token_id: Option<&String>, cause: &str, memo: &Option<String>| {
+        NftMetadataUpdate {
+            event_index: BigDecimal::zero(), // initialized later
+            standard: NFT.to_string(),
+            receipt_id: outcome.receipt.receipt_id.to_string(),
+            block_height: BigDecimal::from(block_header.height),
+            block_timestamp: BigDecimal::from(block_header.timestamp),
+            contract_account_id: outcome.receipt.receiver_id.to_string(),
+            token_id: token_id.map(|s| s.escape_default().to_string()),
+            cause: cause.to_string(),
+            status: get_status(&outcome.execution_outcome.outcome.status),
+            event_memo: memo.as_ref().map(|s| s.escape_default().to_string()),
+            event_version: None, // initialized later
+            unsupported_event_version: false,
+        }
+    };
+
+    let mut metadata_updates = vec![];
+    match &events.event_kind {
+        event_types::Nep171EventKind::ContractMetadataUpdate(update_events) => {
+            for update_event in update_events {
+                metadata_updates.push(build_event(
+                    None,
+                    "CONTRACT_METADATA_UPDATE",
+                    &update_event.memo,
+                ));
+            }
+        }
+        event_types::Nep171EventKind::NftMetadataUpdate(update_events) => {
+            for update_event in update_events {
+                for token_id in &update_event.token_ids {
+                    metadata_updates.push(build_event(
+                        Some(token_id),
+                        "METADATA_UPDATE",
+                        &update_event.memo,
+                    ));
+                }
+            }
+        }
+        event_types::Nep171EventKind::NftMint(_)
+        | event_types::Nep171EventKind::NftTransfer(_)
+        | event_types::Nep171EventKind::NftBurn(_) => {}
+    }
+    metadata_updates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_adapters::nft::nep171_events::collect_nep171_events;
+    use crate::db_adapters::test_utils::collect_fixture;
+    use futures::FutureExt;
+
+    async fn collect_fixture_updates() -> Vec<NftMetadataUpdate> {
+        collect_fixture(
+            "nep171_metadata_updates",
+            |shard_id, outcomes, block_header| {
+                collect_nep171_metadata_updates(
+                    shard_id,
+                    outcomes,
+                    block_header,
+                    EventVersionMode::Strict,
+                )
+                .boxed_local()
+            },
+        )
+        .await
+    }
+
+    // The contract update has no token, the token update gives one row for each token
+    #[tokio::test]
+    async fn collects_contract_and_token_updates() {
+        let events = collect_fixture_updates().await;
+        let rows: Vec<_> = events
+            .iter()
+            .map(|event| {
+                (
+                    event.event_index.to_string(),
+                    event.cause.as_str(),
+                    event.token_id.as_deref(),
+                    event.event_memo.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "17350000030000000000000000010000000".to_string(),
+                    "CONTRACT_METADATA_UPDATE",
+                    None,
+                    None
+                ),
+                (
+                    "17350000030000000000000000010000001".to_string(),
+                    "METADATA_UPDATE",
+                    Some("1"),
+                    Some("new media")
+                ),
+                (
+                    "17350000030000000000000000010000002".to_string(),
+                    "METADATA_UPDATE",
+                    Some("3"),
+                    Some("new media")
+                ),
+                (
+                    "17350000030000000000000000010000003".to_string(),
+                    "METADATA_UPDATE",
+                    Some("2"),
+                    None
+                ),
+            ]
+        );
+        for event in &events {
+            assert_eq!(event.block_height, BigDecimal::from(75000003));
+            assert_eq!(event.standard, NFT);
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_updates_of_failed_receipts() {
+        let events = collect_fixture_updates().await;
+        let failed_tokens: Vec<_> = events
+            .iter()
+            .filter(|event| event.status == "FAILURE")
+            .map(|event| event.token_id.as_deref())
+            .collect();
+        assert_eq!(failed_tokens, vec![Some("2")]);
+    }
+
+    // Metadata updates are parsed, so the mint next to them is not lost
+    #[tokio::test]
+    async fn keeps_mint_next_to_metadata_update() {
+        let nft_events = collect_fixture(
+            "nep171_metadata_updates",
+            |shard_id, outcomes, block_header| {
+                collect_nep171_events(shard_id, outcomes, block_header, EventVersionMode::Strict)
+                    .boxed_local()
+            },
+        )
+        .await;
+        let causes: Vec<_> = nft_events
+            .iter()
+            .map(|event| (event.token_id.as_str(), event.cause.as_str()))
+            .collect();
+        assert_eq!(causes, vec![("3", "MINT")]);
+    }
+}
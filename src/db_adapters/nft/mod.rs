@@ -1,30 +1,71 @@
+use crate::configs::{EventVersionMode, InsertMethod};
 use crate::db_adapters::Event;
 use crate::models;
 use crate::models::nft_events::NftEvent;
+use crate::models::nft_metadata_updates::NftMetadataUpdate;
+use crate::models::rejected_events::RejectedEvent;
 use futures::future::try_join_all;
+use near_jsonrpc_client::JsonRpcClient;
 use near_lake_framework::near_indexer_primitives;
 
+pub(crate) mod legacy;
+mod metadata_updates;
 mod nep171_events;
+pub(crate) mod ownership;
 
 pub const NFT: &str = "NFT_NEP171";
-// pub const NFT_LEGACY: &str = "NFT_LEGACY";
+pub const NFT_LEGACY: &str = "NFT_LEGACY";
 
 pub(crate) async fn store_nft(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     streamer_message: &near_indexer_primitives::StreamerMessage,
-) -> anyhow::Result<()> {
-    let mut nep171_events: Vec<NftEvent> = vec![];
+    chain_id: &str,
+    insert_method: InsertMethod,
+    event_version_mode: EventVersionMode,
+    rpc_client: &JsonRpcClient,
+) -> anyhow::Result<(Vec<NftEvent>, Vec<RejectedEvent>)> {
+    let mut nft_events: Vec<NftEvent> = vec![];
     let nft_events_futures = streamer_message.shards.iter().map(|shard| {
         nep171_events::collect_nep171_events(
             &shard.shard_id,
             &shard.receipt_execution_outcomes,
             &streamer_message.block.header,
+            event_version_mode,
         )
     });
     for events in try_join_all(nft_events_futures).await? {
-        nep171_events.extend(events);
+        nft_events.extend(events);
+    }
+    let legacy_events_futures = streamer_message.shards.iter().map(|shard| {
+        legacy::collect_legacy(
+            &shard.shard_id,
+            &shard.receipt_execution_outcomes,
+            &streamer_message.block.header,
+            chain_id,
+            rpc_client,
+        )
+    });
+    let mut rejected_events: Vec<RejectedEvent> = vec![];
+    for (events, rejected) in try_join_all(legacy_events_futures).await? {
+        nft_events.extend(events);
+        rejected_events.extend(rejected);
+    }
+    models::insert(tx, &nft_events, insert_method).await?;
+
+    let mut metadata_updates: Vec<NftMetadataUpdate> = vec![];
+    let metadata_updates_futures = streamer_message.shards.iter().map(|shard| {
+        metadata_updates::collect_nep171_metadata_updates(
+            &shard.shard_id,
+            &shard.receipt_execution_outcomes,
+            &streamer_message.block.header,
+            event_version_mode,
+        )
+    });
+    for updates in try_join_all(metadata_updates_futures).await? {
+        metadata_updates.extend(updates);
     }
-    models::chunked_insert(pool, &nep171_events).await
+    models::insert(tx, &metadata_updates, insert_method).await?;
+    Ok((nft_events, rejected_events))
 }
 
 // todo it could be one method both for ft and nft
@@ -1,5 +1,6 @@
 use bigdecimal::BigDecimal;
 
+use crate::configs::EventVersionMode;
 use crate::db_adapters::event_types::Nep171Event;
 use crate::db_adapters::Event;
 use crate::db_adapters::{events, get_status, nft};
@@ -14,12 +15,18 @@ pub(crate) async fn collect_nep171_events(
     shard_id: &near_indexer_primitives::types::ShardId,
     receipt_execution_outcomes: &[near_indexer_primitives::IndexerExecutionOutcomeWithReceipt],
     block_header: &near_indexer_primitives::views::BlockHeaderView,
+    event_version_mode: EventVersionMode,
 ) -> anyhow::Result<Vec<NftEvent>> {
     let mut res = Vec::new();
     for outcome in receipt_execution_outcomes {
-        for event in events::extract_events(outcome) {
-            if let event_types::NearEvent::Nep171(nft_events) = event {
-                compose_nft_db_events(&nft_events, outcome, block_header)?;
+        for indexed_event in events::extract_events_with_index(outcome, event_version_mode) {
+            if let event_types::NearEvent::Nep171(nft_events) = &indexed_event.event {
+                let mut nft_db_events = compose_nft_db_events(nft_events, outcome, block_header)?;
+                for nft_db_event in &mut nft_db_events {
+                    nft_db_event.event_version = Some(nft_events.version.clone());
+                    nft_db_event.unsupported_event_version = !indexed_event.is_version_supported;
+                }
+                res.extend(nft_db_events);
             }
         }
     }
@@ -58,6 +65,8 @@ fn compose_nft_db_events(
                             .memo
                             .as_ref()
                             .map(|s| s.escape_default().to_string()),
+                        event_version: None, // initialized later
+                        unsupported_event_version: false,
                     });
                 }
             }
@@ -89,6 +98,8 @@ fn compose_nft_db_events(
                             .memo
                             .as_ref()
                             .map(|s| s.escape_default().to_string()),
+                        event_version: None, // initialized later
+                        unsupported_event_version: false,
                     });
                 }
             }
@@ -118,10 +129,122 @@ fn compose_nft_db_events(
                             .memo
                             .as_ref()
                             .map(|s| s.escape_default().to_string()),
+                        event_version: None, // initialized later
+                        unsupported_event_version: false,
                     });
                 }
             }
         }
+        // Metadata updates do not move the tokens, see `nft::metadata_updates`
+        event_types::Nep171EventKind::ContractMetadataUpdate(_)
+        | event_types::Nep171EventKind::NftMetadataUpdate(_) => {}
     }
     Ok(nft_events)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_adapters::test_utils::collect_fixture;
+    use futures::FutureExt;
+
+    // The fixture also has the plain logs, the malformed event and NEP-141 event, they give no rows
+    async fn collect_fixture_events() -> Vec<NftEvent> {
+        collect_fixture("nep171_events", |shard_id, outcomes, block_header| {
+            collect_nep171_events(shard_id, outcomes, block_header, EventVersionMode::Strict)
+                .boxed_local()
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn enumerates_events_of_the_block() {
+        let events = collect_fixture_events().await;
+        let event_indexes: Vec<_> = events
+            .iter()
+            .map(|event| event.event_index.to_string())
+            .collect();
+        assert_eq!(
+            event_indexes,
+            (0..6)
+                .map(|index| format!("1735000000000000000000000000200000{}", index))
+                .collect::<Vec<_>>()
+        );
+        for event in &events {
+            assert_eq!(event.block_height, BigDecimal::from(75000000));
+            assert_eq!(
+                event.block_timestamp,
+                BigDecimal::from(1735000000000000000u64)
+            );
+            assert_eq!(event.standard, NFT);
+        }
+    }
+
+    // One row for each token of the event, the token ids and the memos are escaped
+    #[tokio::test]
+    async fn collects_mint_and_burn() {
+        let events = collect_fixture_events().await;
+        let rows: Vec<_> = events
+            .iter()
+            .filter(|event| event.cause != "TRANSFER")
+            .map(|event| {
+                (
+                    event.cause.as_str(),
+                    event.token_id.as_str(),
+                    event.old_owner_account_id.as_deref(),
+                    event.new_owner_account_id.as_deref(),
+                    event.event_memo.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("MINT", "1", None, Some("alice.near"), Some("first drop")),
+                ("MINT", "2", None, Some("alice.near"), Some("first drop")),
+                ("MINT", "token:\\u{fc}", None, Some("bob.near"), None),
+                ("BURN", "token:\\u{fc}", Some("bob.near"), None, None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_authorized_account_of_transfer() {
+        let events = collect_fixture_events().await;
+        let rows: Vec<_> = events
+            .iter()
+            .filter(|event| event.receipt_id == "8kZ46vDGN8A2xoodd8ziTmiEqrGC7UbPzS5P61GtkQUn")
+            .map(|event| {
+                (
+                    event.old_owner_account_id.as_deref(),
+                    event.new_owner_account_id.as_deref(),
+                    event.authorized_account_id.as_deref(),
+                    event.event_memo.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![(
+                Some("alice.near"),
+                Some("carol.near"),
+                Some("market.near"),
+                Some("sold")
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_events_of_failed_receipts() {
+        let events = collect_fixture_events().await;
+        let failed: Vec<_> = events
+            .iter()
+            .filter(|event| event.status == "FAILURE")
+            .map(|event| (event.receipt_id.as_str(), event.token_id.as_str()))
+            .collect();
+        assert_eq!(
+            failed,
+            vec![("2n9ukbqU3ZSBEVghAfPduXJiy3NggziaVhd1VaYWNEDG", "2")]
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+
+use crate::configs::InsertMethod;
+use crate::models;
+use crate::models::nft_events::NftEvent;
+use crate::models::nft_ownership::NftOwnership;
+
+// The causes which move the token, the other events do not change the owner
+const OWNERSHIP_CAUSES: [&str; 3] = ["MINT", "TRANSFER", "BURN"];
+
+// The owners are shared between the blocks, so this is called in the order of the blocks.
+// The rows are sorted by the primary key: the concurrent writers always lock them in the same order
+pub(crate) async fn store_nft_ownership(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    events: &[NftEvent],
+    insert_method: InsertMethod,
+) -> anyhow::Result<()> {
+    // One upsert can't touch the same row twice, so we keep only the last event of each token
+    let mut owners: BTreeMap<(&str, &str), NftOwnership> = BTreeMap::new();
+    // The failed receipts do not change the owners
+    for event in events.iter().filter(|event| {
+        event.status == "SUCCESS" && OWNERSHIP_CAUSES.contains(&event.cause.as_str())
+    }) {
+        match owners.get(&(&event.contract_account_id, &event.token_id)) {
+            Some(owner) if owner.last_event_index >= event.event_index => {}
+            _ => {
+                owners.insert(
+                    (&event.contract_account_id, &event.token_id),
+                    NftOwnership {
+                        contract_account_id: event.contract_account_id.clone(),
+                        token_id: event.token_id.clone(),
+                        owner_account_id: event.new_owner_account_id.clone(),
+                        last_event_index: event.event_index.clone(),
+                    },
+                );
+            }
+        }
+    }
+
+    let owners: Vec<NftOwnership> = owners.into_values().collect();
+    models::insert(tx, &owners, insert_method).await
+}
+
+/// Recomputes all the owners from the stored `nft_events`.
+/// The indexers writing to the same DB should be stopped, otherwise their blocks could be lost
+pub(crate) async fn rebuild_nft_ownership(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+) -> anyhow::Result<u64> {
+    let mut tx = pool.begin().await?;
+    sqlx::query("DELETE FROM nft_ownership")
+        .execute(&mut tx)
+        .await?;
+    let rows_count = sqlx::query(
+        "INSERT INTO nft_ownership \
+         SELECT DISTINCT ON (contract_account_id, token_id) \
+         contract_account_id, token_id, new_owner_account_id, event_index \
+         FROM nft_events WHERE status = 'SUCCESS' AND cause IN ('MINT', 'TRANSFER', 'BURN') \
+         ORDER BY contract_account_id, token_id, event_index DESC",
+    )
+    .execute(&mut tx)
+    .await?
+    .rows_affected();
+    tx.commit().await?;
+    Ok(rows_count)
+}
@@ -0,0 +1,146 @@
+use bigdecimal::BigDecimal;
+use futures::future::try_join_all;
+use near_lake_framework::near_indexer_primitives;
+
+use crate::configs::InsertMethod;
+use crate::db_adapters::{events, get_status, Event};
+use crate::models;
+use crate::models::raw_events::RawEvent;
+
+pub const RAW: &str = "NEP297";
+
+pub(crate) async fn store_raw_events(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    streamer_message: &near_indexer_primitives::StreamerMessage,
+    insert_method: InsertMethod,
+) -> anyhow::Result<()> {
+    let mut raw_events: Vec<RawEvent> = vec![];
+    let raw_events_futures = streamer_message.shards.iter().map(|shard| {
+        collect_raw_events(
+            &shard.shard_id,
+            &shard.receipt_execution_outcomes,
+            &streamer_message.block.header,
+        )
+    });
+    for events in try_join_all(raw_events_futures).await? {
+        raw_events.extend(events);
+    }
+    models::insert(tx, &raw_events, insert_method).await
+}
+
+async fn collect_raw_events(
+    shard_id: &near_indexer_primitives::types::ShardId,
+    receipt_execution_outcomes: &[near_indexer_primitives::IndexerExecutionOutcomeWithReceipt],
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+) -> anyhow::Result<Vec<RawEvent>> {
+    let mut res = Vec::new();
+    for outcome in receipt_execution_outcomes {
+        for (log_index, event) in events::extract_raw_events(outcome) {
+            res.push(RawEvent {
+                event_index: BigDecimal::default(), // initialized later
+                standard: event.standard.escape_default().to_string(),
+                version: event.version.escape_default().to_string(),
+                event: event.event.escape_default().to_string(),
+                data: event.data.map(escape_nul),
+                receipt_id: outcome.receipt.receipt_id.to_string(),
+                block_height: BigDecimal::from(block_header.height),
+                block_timestamp: BigDecimal::from(block_header.timestamp),
+                contract_account_id: outcome.receipt.receiver_id.to_string(),
+                status: get_status(&outcome.execution_outcome.outcome.status),
+                log_index: i32::try_from(log_index)?,
+            });
+        }
+    }
+
+    for (index, event) in res.iter_mut().enumerate() {
+        event.event_index = crate::db_adapters::compose_db_index(
+            block_header.timestamp,
+            shard_id,
+            &Event::Raw,
+            index,
+        )?;
+    }
+    Ok(res)
+}
+
+// jsonb can't store NUL character, we keep it escaped the same way as `escape_default` does
+fn escape_nul(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(s) => serde_json::Value::String(s.replace('\0', "\\u{0}")),
+        serde_json::Value::Array(values) => {
+            serde_json::Value::Array(values.into_iter().map(escape_nul).collect())
+        }
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key.replace('\0', "\\u{0}"), escape_nul(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_adapters::test_utils::collect_fixture;
+    use futures::FutureExt;
+
+    // The fixture also has the plain logs, the malformed event and the event without version
+    async fn collect_fixture_events() -> Vec<RawEvent> {
+        collect_fixture("raw_events", |shard_id, outcomes, block_header| {
+            collect_raw_events(shard_id, outcomes, block_header).boxed_local()
+        })
+        .await
+    }
+
+    // NEP-141 event without amount is not valid, but it's still well-formed NEP-297 event
+    #[tokio::test]
+    async fn collects_well_formed_events() {
+        let events = collect_fixture_events().await;
+        let rows: Vec<_> = events
+            .iter()
+            .map(|event| {
+                (
+                    event.standard.as_str(),
+                    event.version.as_str(),
+                    event.event.as_str(),
+                    event.log_index,
+                    event.status.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("ref-finance", "1.0.0", "swap", 1, "SUCCESS"),
+                ("nep141", "1.0.0", "ft_transfer", 2, "SUCCESS"),
+                ("nep141", "1.0.0", "ft_transfer", 3, "SUCCESS"),
+                ("social", "0.1.0", "follow", 0, "FAILURE"),
+            ]
+        );
+        for (index, event) in events.iter().enumerate() {
+            assert_eq!(
+                event.event_index.to_string(),
+                format!("1735000004000000000000000001100000{}", index)
+            );
+            assert_eq!(event.block_height, BigDecimal::from(75000004));
+        }
+    }
+
+    // The data is stored as is, the event without data has `NULL` there
+    #[tokio::test]
+    async fn keeps_event_data() {
+        let events = collect_fixture_events().await;
+        assert_eq!(
+            events[0].data,
+            Some(serde_json::json!([{
+                "token_in": "wrap.near",
+                "amount_in": "10",
+                "token_out": "usdt.near",
+                "amount_out": "35",
+                "note": "a\\u{0}b",
+            }]))
+        );
+        assert_eq!(events[3].data, None);
+    }
+}
@@ -0,0 +1,153 @@
+use bigdecimal::BigDecimal;
+use near_lake_framework::near_indexer_primitives;
+use near_primitives::views::ActionView;
+
+use crate::configs::{EventVersionMode, InsertMethod};
+use crate::db_adapters::events;
+use crate::models;
+use crate::models::rejected_events::RejectedEvent;
+
+// The rejected events are stored together with the rest of the block
+pub(crate) async fn store_rejected_events(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    streamer_message: &near_indexer_primitives::StreamerMessage,
+    mut rejected_events: Vec<RejectedEvent>,
+    insert_method: InsertMethod,
+    event_version_mode: EventVersionMode,
+) -> anyhow::Result<()> {
+    for shard in &streamer_message.shards {
+        for outcome in &shard.receipt_execution_outcomes {
+            for (log_index, err) in events::extract_malformed_events(outcome, event_version_mode) {
+                rejected_events.push(build_rejected_event(
+                    outcome,
+                    &streamer_message.block.header,
+                    log_index,
+                    &err,
+                )?);
+            }
+        }
+    }
+    models::insert(tx, &rejected_events, insert_method).await
+}
+
+pub(crate) fn build_rejected_event(
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    log_index: usize,
+    err: &anyhow::Error,
+) -> anyhow::Result<RejectedEvent> {
+    tracing::warn!(
+        target: crate::LOGGING_PREFIX,
+        "Event log {} of receipt {} is rejected: {:#}",
+        log_index,
+        outcome.receipt.receipt_id,
+        err
+    );
+    let log = outcome
+        .execution_outcome
+        .outcome
+        .logs
+        .get(log_index)
+        .ok_or_else(|| anyhow::anyhow!("Log {} is not found", log_index))?;
+    Ok(RejectedEvent {
+        receipt_id: outcome.receipt.receipt_id.to_string(),
+        log_index: i32::try_from(log_index)?,
+        block_height: BigDecimal::from(block_header.height),
+        block_timestamp: BigDecimal::from(block_header.timestamp),
+        contract_account_id: outcome.receipt.receiver_id.to_string(),
+        // text can't store NUL character, we keep it escaped the same way as `escape_default` does
+        log: log.replace('\0', "\\u{0}"),
+        error: format!("{:#}", err).replace('\0', "\\u{0}"),
+    })
+}
+
+/// The legacy action which can't be collected, see `LegacyHandler::check_action_supported`.
+/// There's no log, so `log_index` is negative: -1 for the first action of the receipt, -2 for the second one, etc.
+/// The action is stored as JSON in the same format as in the receipt
+pub(crate) fn build_rejected_action(
+    outcome: &near_indexer_primitives::IndexerExecutionOutcomeWithReceipt,
+    block_header: &near_indexer_primitives::views::BlockHeaderView,
+    action_index: usize,
+    action: &ActionView,
+    err: &anyhow::Error,
+) -> anyhow::Result<RejectedEvent> {
+    tracing::warn!(
+        target: crate::LOGGING_PREFIX,
+        "Action {} of receipt {} is rejected: {:#}",
+        action_index,
+        outcome.receipt.receipt_id,
+        err
+    );
+    Ok(RejectedEvent {
+        receipt_id: outcome.receipt.receipt_id.to_string(),
+        log_index: -1 - i32::try_from(action_index)?,
+        block_height: BigDecimal::from(block_header.height),
+        block_timestamp: BigDecimal::from(block_header.timestamp),
+        contract_account_id: outcome.receipt.receiver_id.to_string(),
+        log: serde_json::to_string(action)?,
+        error: format!("{:#}", err).replace('\0', "\\u{0}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_adapters::test_utils::read_fixture;
+
+    fn find_fixture_malformed_events() -> Vec<RejectedEvent> {
+        let fixture = read_fixture("raw_events");
+        let mut rejected_events = vec![];
+        for outcome in &fixture.shard.receipt_execution_outcomes {
+            for (log_index, err) in
+                events::extract_malformed_events(outcome, EventVersionMode::Strict)
+            {
+                rejected_events.push(
+                    build_rejected_event(outcome, &fixture.block_header, log_index, &err).unwrap(),
+                );
+            }
+        }
+        rejected_events
+    }
+
+    // The event of the unknown standard is not rejected, it's stored only in `raw_events`
+    #[test]
+    fn finds_malformed_events() {
+        let errors: Vec<_> = find_fixture_malformed_events()
+            .into_iter()
+            .map(|event| (event.receipt_id, event.log_index, event.error))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "3LDdyyu8bscbPybvpMVPN4mrmqKipE9HPTGo7QnprcJN".to_string(),
+                    3,
+                    "Invalid nep141 event: missing field `amount`".to_string(),
+                ),
+                (
+                    "7Wk3ZzkoyFo828WwGwHbNVjg1B77c8ZRsnWTThMRP636".to_string(),
+                    1,
+                    "Not a NEP-297 event: EOF while parsing a value at line 1 column 47"
+                        .to_string(),
+                ),
+                (
+                    "7Wk3ZzkoyFo828WwGwHbNVjg1B77c8ZRsnWTThMRP636".to_string(),
+                    2,
+                    "Not a NEP-297 event: missing field `version` at line 1 column 51".to_string(),
+                ),
+            ]
+        );
+    }
+
+    // The log is stored untouched, so the event could be parsed again later
+    #[test]
+    fn keeps_original_log() {
+        let rejected_events = find_fixture_malformed_events();
+        assert_eq!(
+            rejected_events[1].log,
+            r#"EVENT_JSON:{"standard":"social","version":"0.1.0","event":"#
+        );
+        assert_eq!(rejected_events[1].contract_account_id, "social.near");
+        assert_eq!(rejected_events[1].block_height, BigDecimal::from(75000004));
+    }
+}
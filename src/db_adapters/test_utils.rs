@@ -0,0 +1,95 @@
+use futures::future::LocalBoxFuture;
+use near_lake_framework::near_indexer_primitives;
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// The block header and one shard of the block, in the same format as NEAR Lake stores them.
+/// The fixtures are composed by hand so far, the real blocks are recorded with `tests/fixtures/record.sh`
+#[derive(serde::Deserialize)]
+pub(crate) struct Fixture {
+    pub block_header: near_indexer_primitives::views::BlockHeaderView,
+    pub shard: near_indexer_primitives::IndexerShard,
+}
+
+pub(crate) fn read_fixture(name: &str) -> Fixture {
+    let path = format!(
+        "{}/tests/fixtures/{}.json",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    let fixture = std::fs::read_to_string(&path)
+        .unwrap_or_else(|err| panic!("Failed to read {}: {}", path, err));
+    serde_json::from_str(&fixture).unwrap_or_else(|err| panic!("Invalid {}: {}", path, err))
+}
+
+/// Runs the collector over the shard of the fixture with the same arguments as the indexer does
+pub(crate) async fn collect_fixture<T>(
+    name: &str,
+    collect: impl for<'a> FnOnce(
+        &'a near_indexer_primitives::types::ShardId,
+        &'a [near_indexer_primitives::IndexerExecutionOutcomeWithReceipt],
+        &'a near_indexer_primitives::views::BlockHeaderView,
+    ) -> LocalBoxFuture<'a, anyhow::Result<T>>,
+) -> T {
+    let fixture = read_fixture(name);
+    collect(
+        &fixture.shard.shard_id,
+        &fixture.shard.receipt_execution_outcomes,
+        &fixture.block_header,
+    )
+    .await
+    .unwrap_or_else(|err| panic!("Failed to collect {}: {:#}", name, err))
+}
+
+/// Starts JSON RPC server on a random port, returns its URL.
+/// `respond` gets the request and returns the response without `jsonrpc` and `id`
+pub(crate) fn start_rpc(respond: fn(&serde_json::Value) -> serde_json::Value) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            std::thread::spawn(move || serve_connection(stream, respond));
+        }
+    });
+    url
+}
+
+fn serve_connection(
+    mut stream: std::net::TcpStream,
+    respond: fn(&serde_json::Value) -> serde_json::Value,
+) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    loop {
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+        let request: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let mut response = respond(&request);
+        response["jsonrpc"] = "2.0".into();
+        response["id"] = request["id"].clone();
+        let response = response.to_string();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            response.len(),
+            response
+        )
+        .unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+use near_lake_framework::near_indexer_primitives;
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+use crate::LOGGING_PREFIX;
+
+const BLOCKS_PRELOAD_POOL_SIZE: usize = 100;
+const RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Streams the blocks from the local directory with the same layout as NEAR Lake bucket:
+/// - <block_height>/block.json
+/// - <block_height>/shard_N.json
+///
+/// Block heights are zero-padded to 12 digits in the bucket, but any number format is accepted here.
+/// Unlike Lake, the directory is read once, the stream finishes after the last block.
+pub(crate) fn streamer(
+    blocks_dir: PathBuf,
+    start_block_height: u64,
+    end_block_height: Option<u64>,
+) -> (
+    tokio::task::JoinHandle<anyhow::Result<()>>,
+    mpsc::Receiver<near_indexer_primitives::StreamerMessage>,
+) {
+    let (sender, receiver) = mpsc::channel(BLOCKS_PRELOAD_POOL_SIZE);
+    (
+        tokio::spawn(start(
+            sender,
+            blocks_dir,
+            start_block_height,
+            end_block_height,
+        )),
+        receiver,
+    )
+}
+
+async fn start(
+    streamer_message_sink: mpsc::Sender<near_indexer_primitives::StreamerMessage>,
+    blocks_dir: PathBuf,
+    start_block_height: u64,
+    end_block_height: Option<u64>,
+) -> anyhow::Result<()> {
+    let block_heights = list_blocks(&blocks_dir, start_block_height, end_block_height)?;
+    tracing::info!(
+        target: LOGGING_PREFIX,
+        "Found {} blocks in {}",
+        block_heights.len(),
+        blocks_dir.display()
+    );
+
+    for (block_height, block_dir) in block_heights {
+        let streamer_message = fetch_streamer_message_or_retry(&block_dir, block_height).await?;
+        if streamer_message_sink.send(streamer_message).await.is_err() {
+            // The receiver is dropped, nobody is interested in the blocks anymore
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+fn list_blocks(
+    blocks_dir: &Path,
+    start_block_height: u64,
+    end_block_height: Option<u64>,
+) -> anyhow::Result<Vec<(u64, PathBuf)>> {
+    let mut block_heights = vec![];
+    for entry in std::fs::read_dir(blocks_dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+        let block_height = match entry.file_name().to_str().map(str::parse::<u64>) {
+            Some(Ok(block_height)) => block_height,
+            _ => {
+                tracing::warn!(
+                    target: LOGGING_PREFIX,
+                    "Ignoring {}, it's not a block height",
+                    entry.path().display()
+                );
+                continue;
+            }
+        };
+        if block_height >= start_block_height
+            && end_block_height.map_or(true, |end_block_height| block_height <= end_block_height)
+        {
+            block_heights.push((block_height, entry.path()));
+        }
+    }
+    block_heights.sort_unstable_by_key(|(block_height, _)| *block_height);
+    Ok(block_heights)
+}
+
+// The file could be still being written, so we retry a few times before giving up
+async fn fetch_streamer_message_or_retry(
+    block_dir: &Path,
+    block_height: u64,
+) -> anyhow::Result<near_indexer_primitives::StreamerMessage> {
+    let mut retry_attempt = 0usize;
+    loop {
+        retry_attempt += 1;
+        match fetch_streamer_message(block_dir).await {
+            Ok(streamer_message) => return Ok(streamer_message),
+            Err(err) if retry_attempt < crate::db_adapters::RETRY_COUNT => {
+                tracing::error!(
+                    target: LOGGING_PREFIX,
+                    "Failed to read block {} from {}. Retrying in {}ms...\n {:#?}",
+                    block_height,
+                    block_dir.display(),
+                    RETRY_INTERVAL.as_millis(),
+                    err
+                );
+                tokio::time::sleep(RETRY_INTERVAL).await;
+            }
+            Err(err) => {
+                return Err(err.context(format!(
+                    "Failed to read block {} from {} after {} attempts",
+                    block_height,
+                    block_dir.display(),
+                    retry_attempt
+                )))
+            }
+        }
+    }
+}
+
+async fn fetch_streamer_message(
+    block_dir: &Path,
+) -> anyhow::Result<near_indexer_primitives::StreamerMessage> {
+    let block_view = serde_json::from_slice::<near_indexer_primitives::views::BlockView>(
+        &tokio::fs::read(block_dir.join("block.json")).await?,
+    )?;
+
+    let mut shards = Vec::with_capacity(block_view.chunks.len());
+    for shard_id in 0..block_view.chunks.len() {
+        let shard_path = block_dir.join(format!("shard_{}.json", shard_id));
+        shards.push(serde_json::from_slice::<
+            near_indexer_primitives::IndexerShard,
+        >(&tokio::fs::read(&shard_path).await?)?);
+    }
+
+    Ok(near_indexer_primitives::StreamerMessage {
+        block: block_view,
+        shards,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_adapters::test_utils::read_fixture;
+
+    // The same files as NEAR Lake stores: the block with one chunk and its shard
+    fn write_block(blocks_dir: &Path, dir_name: &str, block_height: u64) {
+        let fixture = read_fixture("nep141_events");
+        let mut header = serde_json::to_value(&fixture.block_header).unwrap();
+        header["height"] = block_height.into();
+        let chunk = serde_json::json!({
+            "chunk_hash": header["hash"],
+            "prev_block_hash": header["prev_hash"],
+            "outcome_root": header["outcome_root"],
+            "prev_state_root": header["prev_state_root"],
+            "encoded_merkle_root": header["chunk_headers_root"],
+            "encoded_length": 0,
+            "height_created": block_height,
+            "height_included": block_height,
+            "shard_id": 0,
+            "gas_used": 0,
+            "gas_limit": 1000,
+            "rent_paid": "0",
+            "validator_reward": "0",
+            "balance_burnt": "0",
+            "outgoing_receipts_root": header["chunk_receipts_root"],
+            "tx_root": header["chunk_tx_root"],
+            "validator_proposals": [],
+            "signature": header["signature"],
+        });
+        let block = serde_json::json!({
+            "author": "test.near",
+            "header": header,
+            "chunks": [chunk],
+        });
+        let block_dir = blocks_dir.join(dir_name);
+        std::fs::create_dir(&block_dir).unwrap();
+        std::fs::write(block_dir.join("block.json"), block.to_string()).unwrap();
+        std::fs::write(
+            block_dir.join("shard_0.json"),
+            serde_json::to_string(&fixture.shard).unwrap(),
+        )
+        .unwrap();
+    }
+
+    async fn read_all(
+        blocks_dir: PathBuf,
+        start_block_height: u64,
+        end_block_height: Option<u64>,
+    ) -> (
+        anyhow::Result<()>,
+        Vec<near_indexer_primitives::StreamerMessage>,
+    ) {
+        let (handle, mut stream) = streamer(blocks_dir, start_block_height, end_block_height);
+        let mut streamer_messages = vec![];
+        while let Some(streamer_message) = stream.recv().await {
+            streamer_messages.push(streamer_message);
+        }
+        (handle.await.unwrap(), streamer_messages)
+    }
+
+    #[tokio::test]
+    async fn reads_blocks_of_the_range_in_order() {
+        let blocks_dir = tempfile::tempdir().unwrap();
+        write_block(blocks_dir.path(), "000000000012", 12);
+        write_block(blocks_dir.path(), "11", 11);
+        write_block(blocks_dir.path(), "000000000010", 10);
+        write_block(blocks_dir.path(), "000000000013", 13);
+        std::fs::create_dir(blocks_dir.path().join("not_a_block")).unwrap();
+        std::fs::write(blocks_dir.path().join("14"), "").unwrap();
+
+        let (result, streamer_messages) =
+            read_all(blocks_dir.path().to_path_buf(), 11, Some(12)).await;
+        result.unwrap();
+        let heights: Vec<_> = streamer_messages
+            .iter()
+            .map(|streamer_message| streamer_message.block.header.height)
+            .collect();
+        assert_eq!(heights, vec![11, 12]);
+        assert_eq!(streamer_messages[0].shards.len(), 1);
+        assert_eq!(
+            streamer_messages[0].shards[0]
+                .receipt_execution_outcomes
+                .len(),
+            read_fixture("nep141_events")
+                .shard
+                .receipt_execution_outcomes
+                .len()
+        );
+
+        // The stream finishes after the last block of the directory
+        let (result, streamer_messages) = read_all(blocks_dir.path().to_path_buf(), 12, None).await;
+        result.unwrap();
+        assert_eq!(streamer_messages.len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_on_broken_block() {
+        let blocks_dir = tempfile::tempdir().unwrap();
+        write_block(blocks_dir.path(), "10", 10);
+        write_block(blocks_dir.path(), "11", 11);
+        std::fs::write(blocks_dir.path().join("11").join("shard_0.json"), "{").unwrap();
+        write_block(blocks_dir.path(), "12", 12);
+
+        let (result, streamer_messages) = read_all(blocks_dir.path().to_path_buf(), 10, None).await;
+        assert_eq!(streamer_messages.len(), 1);
+        let err = result.unwrap_err();
+        assert!(err.to_string().starts_with(&format!(
+            "Failed to read block 11 from {}",
+            blocks_dir.path().display()
+        )));
+        assert!(err.to_string().ends_with("after 10 attempts"));
+    }
+}
@@ -1,14 +1,17 @@
 // TODO cleanup imports in all the files in the end
-use crate::configs::{init_tracing, Opts};
+use crate::configs::{init_tracing, Command, Opts};
 use clap::Parser;
 use dotenv::dotenv;
 use futures::StreamExt;
 use near_lake_framework::near_indexer_primitives;
 use std::env;
 mod configs;
+mod coordinator;
 mod db_adapters;
+mod local_lake;
 mod metrics;
 mod models;
+mod shutdown;
 
 #[macro_use]
 extern crate lazy_static;
@@ -24,61 +27,304 @@ pub struct AccountWithContract {
     pub contract_account_id: near_primitives::types::AccountId,
 }
 
+/// The place where the indexer reports the last fully stored block
+pub(crate) enum Checkpoint {
+    Indexer {
+        indexer_id: String,
+    },
+    Lease {
+        worker_id: String,
+        range_start: u64,
+        lease_timeout: std::time::Duration,
+    },
+}
+
+impl Checkpoint {
+    // Written in the transaction of the block, so the checkpoint never points to the partially stored block
+    async fn store(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        block_height: u64,
+    ) -> anyhow::Result<()> {
+        match self {
+            Checkpoint::Indexer { indexer_id } => {
+                db_adapters::checkpoints::store_checkpoint(tx, indexer_id, block_height).await
+            }
+            Checkpoint::Lease {
+                worker_id,
+                range_start,
+                lease_timeout,
+            } => {
+                db_adapters::leases::store_lease_progress(
+                    tx,
+                    worker_id,
+                    *range_start,
+                    block_height,
+                    *lease_timeout,
+                )
+                .await
+            }
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenv().ok();
     let opts: Opts = Opts::parse();
 
-    let pool = sqlx::PgPool::connect(&env::var("DATABASE_URL")?).await?;
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .max_connections(opts.max_db_connections())
+        .connect(&env::var("DATABASE_URL")?)
+        .await?;
 
     let _worker_guard = init_tracing(opts.debug)?;
+    if let Some(command) = opts.command {
+        return run_command(&pool, command).await;
+    }
+    db_adapters::coin::legacy::init_legacy_handlers(opts.legacy_ft_contracts_config.as_deref())?;
+    db_adapters::nft::legacy::init_legacy_handlers(opts.legacy_nft_contracts_config.as_deref())?;
+    let shutdown = shutdown::init_shutdown_signal()?;
 
-    let config: near_lake_framework::LakeConfig = opts.to_lake_config().await;
-    let (_lake_handle, stream) = near_lake_framework::streamer(config);
-
-    tokio::spawn(async move {
-        let mut handlers = tokio_stream::wrappers::ReceiverStream::new(stream)
-            .map(|streamer_message| {
-                handle_streamer_message(streamer_message, &pool, &opts.chain_id)
-            })
-            .buffer_unordered(1usize);
-
-        let mut time_now = std::time::Instant::now();
-        while let Some(handle_message) = handlers.next().await {
-            match handle_message {
-                Ok(block_height) => {
-                    let elapsed = time_now.elapsed();
-                    tracing::info!(
-                        target: LOGGING_PREFIX,
-                        "Elapsed time spent on block {}: {:.3?}",
-                        block_height,
-                        elapsed
-                    );
-                    time_now = std::time::Instant::now();
-                }
-                Err(e) => {
-                    tracing::error!(target: LOGGING_PREFIX, "Stop indexing due to {}", e);
-                    // we do not catch this error anywhere, this thread is just stopped with error,
-                    // main thread continues serving metrics
-                    anyhow::bail!(e)
-                }
+    let port = opts.port;
+    let max_block_lag = std::time::Duration::from_secs(opts.max_block_lag_secs);
+    let exit_on_failure = opts.exit_on_failure;
+    let indexer = if opts.coordinator {
+        tokio::spawn(coordinator::run(pool, opts, shutdown.clone()))
+    } else {
+        let start_block_height = opts.get_start_block_height(&pool).await?;
+        let end_block_height = opts.get_end_block_height(start_block_height)?;
+        let (streamer_handle, stream) =
+            streamer(&opts, start_block_height, end_block_height).await?;
+        let checkpoint = Checkpoint::Indexer {
+            indexer_id: opts.indexer_id.clone(),
+        };
+
+        let indexer_shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            index_blocks(
+                &pool,
+                streamer_handle,
+                stream,
+                end_block_height,
+                &checkpoint,
+                &opts,
+                indexer_shutdown,
+            )
+            .await
+        })
+    };
+
+    metrics::set_indexing_alive(true);
+
+    let metrics_server = metrics::init_metrics_server(port, max_block_lag)?;
+    let metrics_server_handle = metrics_server.handle();
+    let mut metrics_server = tokio::spawn(metrics_server);
+
+    let indexer_result = tokio::select! {
+        result = indexer => result?,
+        result = &mut metrics_server => {
+            return result?.map_err(|e| anyhow::anyhow!("Error while executing HTTP Server: {}", e))
+        }
+    };
+
+    metrics::set_indexing_alive(false);
+    match indexer_result {
+        Ok(()) => tracing::info!(target: LOGGING_PREFIX, "Indexing is finished"),
+        Err(e) if exit_on_failure => {
+            metrics_server_handle.stop(true).await;
+            return Err(e);
+        }
+        // we do not catch this error anywhere, the indexer is just stopped with error,
+        // main thread continues serving metrics (and failing `/health`) until the shutdown
+        Err(e) => {
+            tracing::error!(target: LOGGING_PREFIX, "Stop indexing due to {:#}", e);
+            shutdown::wait_for_shutdown(shutdown).await
+        }
+    }
+    metrics_server_handle.stop(true).await;
+    Ok(())
+}
+
+async fn run_command(pool: &sqlx::Pool<sqlx::Postgres>, command: Command) -> anyhow::Result<()> {
+    match command {
+        Command::RebuildFtBalances => {
+            let rows_count = db_adapters::coin::ft_balances::rebuild_ft_balances(pool).await?;
+            tracing::info!(
+                target: LOGGING_PREFIX,
+                "ft_balances is rebuilt, {} balances stored",
+                rows_count
+            );
+        }
+        Command::RebuildNftOwnership => {
+            let rows_count = db_adapters::nft::ownership::rebuild_nft_ownership(pool).await?;
+            tracing::info!(
+                target: LOGGING_PREFIX,
+                "nft_ownership is rebuilt, {} tokens stored",
+                rows_count
+            );
+        }
+    }
+    Ok(())
+}
+
+async fn streamer(
+    opts: &Opts,
+    start_block_height: u64,
+    end_block_height: Option<u64>,
+) -> anyhow::Result<(
+    tokio::task::JoinHandle<anyhow::Result<()>>,
+    tokio::sync::mpsc::Receiver<near_indexer_primitives::StreamerMessage>,
+)> {
+    Ok(match &opts.blocks_dir {
+        Some(blocks_dir) => {
+            local_lake::streamer(blocks_dir.clone(), start_block_height, end_block_height)
+        }
+        None => {
+            let config: near_lake_framework::LakeConfig =
+                opts.to_lake_config(start_block_height).await?;
+            near_lake_framework::streamer(config)
+        }
+    })
+}
+
+async fn index_blocks(
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    streamer_handle: tokio::task::JoinHandle<anyhow::Result<()>>,
+    stream: tokio::sync::mpsc::Receiver<near_indexer_primitives::StreamerMessage>,
+    end_block_height: Option<u64>,
+    checkpoint: &Checkpoint,
+    opts: &Opts,
+    shutdown: tokio::sync::watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let rpc_client = opts.rpc_client();
+    let reconciliation_rpc_client = opts.reconcile_balances.then_some(&rpc_client);
+    let absolute_amounts_rpc_client = opts.fill_absolute_amounts.then_some(&rpc_client);
+    // Absolute amounts are maintained over the continuous range of blocks
+    let mut balances = db_adapters::coin::absolute_amounts::Balances::new();
+    let mut handlers = tokio_stream::wrappers::ReceiverStream::new(stream)
+        // We stop pulling new blocks on shutdown, the blocks which are already taken are finished below
+        .take_until(Box::pin(shutdown::wait_for_shutdown(shutdown.clone())))
+        // The stream is endless, so we cut it by ourselves in the bounded mode
+        .take_while(|streamer_message| {
+            futures::future::ready(end_block_height.map_or(true, |end_block_height| {
+                streamer_message.block.header.height <= end_block_height
+            }))
+        })
+        .map(|streamer_message| {
+            handle_streamer_message(
+                streamer_message,
+                pool,
+                &rpc_client,
+                reconciliation_rpc_client,
+                opts,
+            )
+        })
+        // `buffered` yields the results in the order of the stream,
+        // so we never move the checkpoint over the block which is not stored yet
+        .buffered(opts.concurrency.get());
+
+    let shutdown_timeout = std::time::Duration::from_secs(opts.shutdown_timeout_secs);
+    let drain_deadline = async {
+        shutdown::wait_for_shutdown(shutdown.clone()).await;
+        tokio::time::sleep(shutdown_timeout).await;
+    };
+    tokio::pin!(drain_deadline);
+
+    let mut time_now = std::time::Instant::now();
+    loop {
+        let handle_message = tokio::select! {
+            handle_message = handlers.next() => match handle_message {
+                Some(handle_message) => handle_message,
+                None => break,
+            },
+            _ = &mut drain_deadline => {
+                tracing::warn!(
+                    target: LOGGING_PREFIX,
+                    "In-flight blocks are not stored in {:?} after the shutdown signal, dropping them",
+                    shutdown_timeout
+                );
+                break;
+            }
+        };
+        let (streamer_message, pending_block) = handle_message?;
+        let block_height = pending_block.block_height;
+        // The retries of the commit are limited by the same deadline,
+        // the transaction of the block is rolled back if it's not committed in time
+        tokio::select! {
+            result = commit_block(
+                streamer_message,
+                pending_block,
+                pool,
+                &rpc_client,
+                absolute_amounts_rpc_client,
+                reconciliation_rpc_client,
+                &mut balances,
+                checkpoint,
+                opts,
+            ) => result?,
+            _ = &mut drain_deadline => {
+                tracing::warn!(
+                    target: LOGGING_PREFIX,
+                    "Block {} is not stored in {:?} after the shutdown signal, dropping it",
+                    block_height,
+                    shutdown_timeout
+                );
+                break;
             }
         }
-        Ok(()) // unreachable statement, loop above is endless
-    });
+        // Prometheus Gauge Metric type do not support u64
+        // https://github.com/tikv/rust-prometheus/issues/470
+        metrics::LATEST_BLOCK_HEIGHT.set(i64::try_from(block_height)?);
+        metrics::mark_block_stored();
+        let elapsed = time_now.elapsed();
+        tracing::info!(
+            target: LOGGING_PREFIX,
+            "Elapsed time spent on block {}: {:.3?}",
+            block_height,
+            elapsed
+        );
+        time_now = std::time::Instant::now();
+        if end_block_height == Some(block_height) {
+            break;
+        }
+    }
 
-    metrics::init_metrics_server(opts.port).await
+    if shutdown::is_shutdown_requested(&shutdown) {
+        tracing::info!(
+            target: LOGGING_PREFIX,
+            "Indexing is stopped by the shutdown signal"
+        );
+        return Ok(());
+    }
+
+    // The local streamer stops with an error on the broken files, the stream just looks finished then.
+    // It returns right away once nobody receives the blocks, unlike Lake which is endless
+    drop(handlers);
+    if opts.blocks_dir.is_some() {
+        streamer_handle.await??;
+    }
+
+    match end_block_height {
+        // All the blocks are awaited above, so all the data is already written to the DB
+        Some(_) => Ok(()),
+        // Local directory is finite, unlike NEAR Lake
+        None if opts.blocks_dir.is_some() => Ok(()),
+        None => anyhow::bail!("Stream of blocks has finished unexpectedly"),
+    }
 }
 
 async fn handle_streamer_message(
     streamer_message: near_indexer_primitives::StreamerMessage,
     pool: &sqlx::Pool<sqlx::Postgres>,
-    chain_id: &str,
-) -> anyhow::Result<u64> {
+    rpc_client: &near_jsonrpc_client::JsonRpcClient,
+    reconciliation_rpc_client: Option<&near_jsonrpc_client::JsonRpcClient>,
+    opts: &Opts,
+) -> anyhow::Result<(
+    near_indexer_primitives::StreamerMessage,
+    db_adapters::events::PendingBlock,
+)> {
     metrics::BLOCK_PROCESSED_TOTAL.inc();
-    // Prometheus Gauge Metric type do not support u64
-    // https://github.com/tikv/rust-prometheus/issues/470
-    metrics::LATEST_BLOCK_HEIGHT.set(i64::try_from(streamer_message.block.header.height)?);
 
     if streamer_message.block.header.height % 100 == 0 {
         tracing::info!(
@@ -89,7 +335,123 @@ async fn handle_streamer_message(
         );
     }
 
-    db_adapters::events::store_events(pool, &streamer_message, chain_id).await?;
+    let pending_block = db_adapters::events::store_events(
+        pool,
+        &streamer_message,
+        &opts.chain_id,
+        opts.insert_method,
+        opts.event_version_mode,
+        rpc_client,
+        reconciliation_rpc_client,
+    )
+    .await?;
+    Ok((streamer_message, pending_block))
+}
+
+// The rows shared between the blocks are written right before the commit, any of these steps could fail.
+// The transaction is already broken then, so the whole block is stored again on the fresh one
+#[allow(clippy::too_many_arguments)]
+async fn commit_block(
+    streamer_message: near_indexer_primitives::StreamerMessage,
+    mut pending_block: db_adapters::events::PendingBlock,
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    rpc_client: &near_jsonrpc_client::JsonRpcClient,
+    absolute_amounts_rpc_client: Option<&near_jsonrpc_client::JsonRpcClient>,
+    reconciliation_rpc_client: Option<&near_jsonrpc_client::JsonRpcClient>,
+    balances: &mut db_adapters::coin::absolute_amounts::Balances,
+    checkpoint: &Checkpoint,
+    opts: &Opts,
+) -> anyhow::Result<()> {
+    let block_height = pending_block.block_height;
+    let mut interval = INTERVAL;
+    let mut retry_attempt = 0usize;
+
+    loop {
+        retry_attempt += 1;
+        let result = match finish_block(
+            &mut pending_block,
+            absolute_amounts_rpc_client,
+            balances,
+            checkpoint,
+            opts,
+        )
+        .await
+        {
+            Ok(block_balances) => pending_block
+                .tx
+                .commit()
+                .await
+                .map(|_| block_balances)
+                .map_err(anyhow::Error::from),
+            Err(err) => {
+                // The connection of the broken transaction is released before the retry takes the new one
+                if let Err(rollback_err) = pending_block.tx.rollback().await {
+                    tracing::warn!(
+                        target: LOGGING_PREFIX,
+                        "Failed to roll back block {}: {:#}",
+                        block_height,
+                        rollback_err
+                    );
+                }
+                Err(err)
+            }
+        };
+        match result {
+            Ok(block_balances) => {
+                balances.update(block_balances);
+                return Ok(());
+            }
+            Err(err) if retry_attempt < db_adapters::RETRY_COUNT => {
+                tracing::warn!(
+                    target: LOGGING_PREFIX,
+                    "Error occurred during committing block {}:\n{:#?}\n Retrying in {} milliseconds...",
+                    block_height,
+                    err,
+                    interval.as_millis(),
+                );
+                tokio::time::sleep(interval).await;
+                if interval < MAX_DELAY_TIME {
+                    interval *= 2;
+                }
+                pending_block = db_adapters::events::store_events(
+                    pool,
+                    &streamer_message,
+                    &opts.chain_id,
+                    opts.insert_method,
+                    opts.event_version_mode,
+                    rpc_client,
+                    reconciliation_rpc_client,
+                )
+                .await?;
+            }
+            Err(err) => {
+                return Err(err.context(format!(
+                    "Failed to commit block {} after {} attempts. Stop trying.",
+                    block_height, retry_attempt
+                )))
+            }
+        }
+    }
+}
 
-    Ok(streamer_message.block.header.height)
+async fn finish_block(
+    pending_block: &mut db_adapters::events::PendingBlock,
+    absolute_amounts_rpc_client: Option<&near_jsonrpc_client::JsonRpcClient>,
+    balances: &mut db_adapters::coin::absolute_amounts::Balances,
+    checkpoint: &Checkpoint,
+    opts: &Opts,
+) -> anyhow::Result<db_adapters::coin::absolute_amounts::BlockBalances> {
+    // The ranges of coordinator mode are indexed in any order, `ft_balances` would skip the earlier blocks
+    let block_balances = db_adapters::events::finish_block(
+        pending_block,
+        absolute_amounts_rpc_client,
+        balances,
+        opts.insert_method,
+        !opts.coordinator,
+    )
+    .await?;
+    checkpoint
+        .store(&mut pending_block.tx, pending_block.block_height)
+        .await?;
+    Ok(block_balances)
 }
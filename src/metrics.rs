@@ -1,4 +1,4 @@
-use actix_web::{get, App, HttpServer, Responder};
+use actix_web::{get, web, App, HttpResponse, HttpServer, Responder};
 use prometheus::{Encoder, IntCounter, IntGauge, Opts};
 
 use crate::LOGGING_PREFIX;
@@ -27,9 +27,75 @@ lazy_static! {
     .unwrap();
     pub(crate) static ref LATEST_BLOCK_HEIGHT: IntGauge = try_create_int_gauge(
         "indexer_events_latest_block_height",
-        "Last seen block height by indexer"
+        "Last block height fully stored by indexer"
     )
     .unwrap();
+    pub(crate) static ref COMPLETED_RANGES: IntGauge = try_create_int_gauge(
+        "indexer_events_coordinator_completed_ranges",
+        "Number of completed block ranges in coordinator mode, counting the ranges of all the workers"
+    )
+    .unwrap();
+    pub(crate) static ref TOTAL_RANGES: IntGauge = try_create_int_gauge(
+        "indexer_events_coordinator_total_ranges",
+        "Total number of block ranges in coordinator mode"
+    )
+    .unwrap();
+    pub(crate) static ref INDEXING_ALIVE: IntGauge = try_create_int_gauge(
+        "indexer_events_indexing_alive",
+        "1 while the indexing loop is running, 0 after it has stopped or failed"
+    )
+    .unwrap();
+    static ref LATEST_BLOCK_STORED_AT: IntGauge = try_create_int_gauge(
+        "indexer_events_latest_block_stored_at",
+        "Unix timestamp (seconds) when the last block was fully stored by indexer"
+    )
+    .unwrap();
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64)
+}
+
+// The staleness is counted from the start, so the indexer has time to store the first block
+pub(crate) fn set_indexing_alive(alive: bool) {
+    if alive {
+        LATEST_BLOCK_STORED_AT.set(unix_now());
+    }
+    INDEXING_ALIVE.set(alive as i64);
+}
+
+pub(crate) fn mark_block_stored() {
+    LATEST_BLOCK_STORED_AT.set(unix_now());
+}
+
+struct HealthConfig {
+    max_block_lag: std::time::Duration,
+}
+
+/// Liveness: the indexing loop has not died
+#[get("/health")]
+async fn get_health() -> impl Responder {
+    if INDEXING_ALIVE.get() == 1 {
+        HttpResponse::Ok().body("OK")
+    } else {
+        HttpResponse::ServiceUnavailable().body("Indexing is stopped")
+    }
+}
+
+/// Readiness: the indexing loop is alive and keeps storing the blocks
+#[get("/ready")]
+async fn get_ready(config: web::Data<HealthConfig>) -> impl Responder {
+    if INDEXING_ALIVE.get() != 1 {
+        return HttpResponse::ServiceUnavailable().body("Indexing is stopped");
+    }
+    let lag_secs = unix_now() - LATEST_BLOCK_STORED_AT.get();
+    if lag_secs > config.max_block_lag.as_secs() as i64 {
+        return HttpResponse::ServiceUnavailable()
+            .body(format!("No blocks were stored for {} seconds", lag_secs));
+    }
+    HttpResponse::Ok().body("OK")
 }
 
 #[get("/metrics")]
@@ -54,15 +120,25 @@ async fn get_metrics() -> impl Responder {
     }
 }
 
-pub(crate) async fn init_metrics_server(port: u16) -> anyhow::Result<()> {
+pub(crate) fn init_metrics_server(
+    port: u16,
+    max_block_lag: std::time::Duration,
+) -> anyhow::Result<actix_web::dev::Server> {
     tracing::info!(
         target: LOGGING_PREFIX,
         "Starting metrics server on http://0.0.0.0:{port}/metrics"
     );
 
-    HttpServer::new(|| App::new().service(get_metrics))
-        .bind(("0.0.0.0", port))?
-        .run()
-        .await
-        .map_err(|e| anyhow::anyhow!("Error while executing HTTP Server: {}", e))
+    let health_config = web::Data::new(HealthConfig { max_block_lag });
+    // Signals are handled by ourselves, the server is stopped after the indexer
+    Ok(HttpServer::new(move || {
+        App::new()
+            .app_data(health_config.clone())
+            .service(get_metrics)
+            .service(get_health)
+            .service(get_ready)
+    })
+    .disable_signals()
+    .bind(("0.0.0.0", port))?
+    .run())
 }
@@ -1,7 +1,5 @@
+use crate::models::{FieldCount, RowArguments};
 use bigdecimal::BigDecimal;
-use sqlx::Arguments;
-
-use crate::models::FieldCount;
 
 #[derive(Debug, sqlx::FromRow, FieldCount)]
 pub struct CoinEvent {
@@ -17,10 +15,13 @@ pub struct CoinEvent {
     pub cause: String,
     pub status: String,
     pub event_memo: Option<String>,
+    pub absolute_amount: Option<BigDecimal>,
+    pub event_version: Option<String>,
+    pub unsupported_event_version: bool,
 }
 
 impl crate::models::SqlMethods for CoinEvent {
-    fn add_to_args(&self, args: &mut sqlx::postgres::PgArguments) {
+    fn add_to_args(&self, args: &mut impl RowArguments) {
         args.add(&self.event_index);
         args.add(&self.standard);
         args.add(&self.receipt_id);
@@ -33,6 +34,9 @@ impl crate::models::SqlMethods for CoinEvent {
         args.add(&self.cause);
         args.add(&self.status);
         args.add(&self.event_memo);
+        args.add(&self.absolute_amount);
+        args.add(&self.event_version);
+        args.add(self.unsupported_event_version);
     }
 
     fn insert_query(items_count: usize) -> anyhow::Result<String> {
@@ -41,6 +45,13 @@ impl crate::models::SqlMethods for CoinEvent {
             + " ON CONFLICT DO NOTHING")
     }
 
+    fn insert_from_query(table: &str) -> String {
+        format!(
+            "INSERT INTO coin_events SELECT * FROM {} ON CONFLICT DO NOTHING",
+            table
+        )
+    }
+
     fn name() -> String {
         "coin_events".to_string()
     }
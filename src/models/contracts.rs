@@ -1,7 +1,5 @@
+use crate::models::{FieldCount, RowArguments};
 use bigdecimal::BigDecimal;
-use sqlx::Arguments;
-
-use crate::models::FieldCount;
 
 #[derive(Debug, Clone, sqlx::FromRow, FieldCount)]
 pub struct Contract {
@@ -13,8 +11,17 @@ pub struct Contract {
     pub inconsistency_found_at_block_height: Option<BigDecimal>,
 }
 
+// The standard is taken from the earliest event, the blocks could come in any order
+const ON_CONFLICT: &str = " ON CONFLICT (contract_account_id) DO UPDATE SET \
+    standard = CASE WHEN excluded.first_event_at_block_height < contracts.first_event_at_block_height \
+        THEN excluded.standard ELSE contracts.standard END, \
+    first_event_at_timestamp = least(contracts.first_event_at_timestamp, excluded.first_event_at_timestamp), \
+    first_event_at_block_height = least(contracts.first_event_at_block_height, excluded.first_event_at_block_height), \
+    inconsistency_found_at_timestamp = least(contracts.inconsistency_found_at_timestamp, excluded.inconsistency_found_at_timestamp), \
+    inconsistency_found_at_block_height = least(contracts.inconsistency_found_at_block_height, excluded.inconsistency_found_at_block_height)";
+
 impl crate::models::SqlMethods for Contract {
-    fn add_to_args(&self, args: &mut sqlx::postgres::PgArguments) {
+    fn add_to_args(&self, args: &mut impl RowArguments) {
         args.add(&self.contract_account_id);
         args.add(&self.standard);
         args.add(&self.first_event_at_timestamp);
@@ -26,11 +33,14 @@ impl crate::models::SqlMethods for Contract {
     fn insert_query(items_count: usize) -> anyhow::Result<String> {
         Ok("INSERT INTO contracts VALUES ".to_owned()
             + &crate::models::create_placeholders(items_count, Contract::field_count())?
-            + " ON CONFLICT (contract_account_id) DO UPDATE SET "
-            + " first_event_at_timestamp = least(contracts.first_event_at_timestamp, excluded.first_event_at_timestamp), "
-            + " first_event_at_block_height = least(contracts.first_event_at_block_height, excluded.first_event_at_block_height), "
-            + " inconsistency_found_at_timestamp = least(contracts.inconsistency_found_at_timestamp, excluded.inconsistency_found_at_timestamp), "
-            + " inconsistency_found_at_block_height = least(contracts.inconsistency_found_at_block_height, excluded.inconsistency_found_at_block_height)")
+            + ON_CONFLICT)
+    }
+
+    fn insert_from_query(table: &str) -> String {
+        format!(
+            "INSERT INTO contracts SELECT * FROM {}{}",
+            table, ON_CONFLICT
+        )
     }
 
     fn name() -> String {
@@ -0,0 +1,179 @@
+use sqlx::postgres::PgArgumentBuffer;
+
+use crate::models::{RowArguments, SqlMethods};
+
+// https://www.postgresql.org/docs/current/sql-copy.html#id-1.9.3.55.9.4
+const BINARY_COPY_SIGNATURE: &[u8] = b"PGCOPY\n\xff\r\n\0";
+
+/// Rows in the binary `COPY` format. The fields are encoded the same way as the query arguments,
+/// so only the types which do not need the OID lookup are supported (all the built-in ones)
+struct CopyRows {
+    buffer: PgArgumentBuffer,
+    // Position of the fields count of the current row
+    row_offset: usize,
+    fields_count: i16,
+}
+
+impl CopyRows {
+    fn new() -> Self {
+        let mut buffer = PgArgumentBuffer::default();
+        buffer.extend_from_slice(BINARY_COPY_SIGNATURE);
+        // Flags and the length of the header extension
+        buffer.extend_from_slice(&0_i32.to_be_bytes());
+        buffer.extend_from_slice(&0_i32.to_be_bytes());
+        Self {
+            buffer,
+            row_offset: 0,
+            fields_count: 0,
+        }
+    }
+
+    fn add_row<T: SqlMethods>(&mut self, item: &T) {
+        self.row_offset = self.buffer.len();
+        self.fields_count = 0;
+        self.buffer.extend_from_slice(&0_i16.to_be_bytes());
+        item.add_to_args(self);
+        self.buffer[self.row_offset..self.row_offset + 2]
+            .copy_from_slice(&self.fields_count.to_be_bytes());
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.buffer.extend_from_slice(&(-1_i16).to_be_bytes());
+        std::mem::take(&mut *self.buffer)
+    }
+}
+
+impl RowArguments for CopyRows {
+    fn add<'q, T>(&mut self, value: T)
+    where
+        T: 'q + Send + sqlx::Encode<'q, sqlx::Postgres> + sqlx::Type<sqlx::Postgres>,
+    {
+        let offset = self.buffer.len();
+        self.buffer.extend_from_slice(&0_i32.to_be_bytes());
+        let len = match value.encode_by_ref(&mut self.buffer) {
+            sqlx::encode::IsNull::No => (self.buffer.len() - offset - 4) as i32,
+            sqlx::encode::IsNull::Yes => -1,
+        };
+        self.buffer[offset..offset + 4].copy_from_slice(&len.to_be_bytes());
+        self.fields_count += 1;
+    }
+}
+
+// The rows are copied to the temporary table first, `COPY` can't skip the rows which are already stored.
+// The temporary table lives as long as the connection, it's emptied right after the rows are moved
+pub(crate) async fn copy_insert<T: SqlMethods>(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    items: &[T],
+) -> anyhow::Result<()> {
+    if items.is_empty() {
+        return Ok(());
+    }
+    let staging_table = format!("{}_staging", T::name());
+    sqlx::query(&format!(
+        "CREATE TEMPORARY TABLE IF NOT EXISTS {} (LIKE {}) ON COMMIT DELETE ROWS",
+        staging_table,
+        T::name()
+    ))
+    .execute(&mut *tx)
+    .await?;
+
+    let mut rows = CopyRows::new();
+    for item in items {
+        rows.add_row(item);
+    }
+    let mut copy = tx
+        .copy_in_raw(&format!(
+            "COPY {} FROM STDIN (FORMAT binary)",
+            staging_table
+        ))
+        .await?;
+    copy.send(rows.finish()).await?;
+    copy.finish().await?;
+
+    sqlx::query(&T::insert_from_query(&staging_table))
+        .execute(&mut *tx)
+        .await?;
+    // The same table could be used again in this transaction, e.g. for the next block part
+    sqlx::query(&format!("TRUNCATE {}", staging_table))
+        .execute(&mut *tx)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bigdecimal::BigDecimal;
+    use std::str::FromStr;
+
+    struct TestRow {
+        memo: Option<String>,
+        amount: BigDecimal,
+        args: serde_json::Value,
+    }
+
+    impl SqlMethods for TestRow {
+        fn add_to_args(&self, args: &mut impl RowArguments) {
+            args.add(self.memo.clone());
+            args.add(self.amount.clone());
+            args.add(self.args.clone());
+        }
+
+        fn insert_query(items_count: usize) -> anyhow::Result<String> {
+            Ok("INSERT INTO test_rows VALUES ".to_owned()
+                + &crate::models::create_placeholders(items_count, 3)?)
+        }
+
+        fn insert_from_query(table: &str) -> String {
+            format!("INSERT INTO test_rows SELECT * FROM {}", table)
+        }
+
+        fn name() -> String {
+            "test_rows".to_string()
+        }
+    }
+
+    #[test]
+    fn encodes_rows_in_binary_copy_format() {
+        let mut rows = CopyRows::new();
+        rows.add_row(&TestRow {
+            memo: None,
+            amount: BigDecimal::from_str("12.5").unwrap(),
+            args: serde_json::json!({"a": 1}),
+        });
+        rows.add_row(&TestRow {
+            memo: Some("hi".to_string()),
+            amount: BigDecimal::from(0),
+            args: serde_json::Value::Null,
+        });
+
+        let mut expected: Vec<u8> = b"PGCOPY\n\xff\r\n\0".to_vec();
+        // Flags, header extension
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+
+        // Fields count
+        expected.extend_from_slice(&[0, 3]);
+        // NULL has the length -1 and no data
+        expected.extend_from_slice(&[0xff, 0xff, 0xff, 0xff]);
+        // NUMERIC: ndigits 2, weight 0, positive sign, dscale 1, base 10000 digits 12 and 5000
+        expected.extend_from_slice(&[0, 0, 0, 12]);
+        expected.extend_from_slice(&[0, 2, 0, 0, 0, 0, 0, 1, 0, 12, 0x13, 0x88]);
+        // JSONB: version 1 and the text
+        expected.extend_from_slice(&[0, 0, 0, 8, 1]);
+        expected.extend_from_slice(br#"{"a":1}"#);
+
+        expected.extend_from_slice(&[0, 3]);
+        expected.extend_from_slice(&[0, 0, 0, 2]);
+        expected.extend_from_slice(b"hi");
+        // NUMERIC zero has no digits
+        expected.extend_from_slice(&[0, 0, 0, 8]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0]);
+        // JSON null is not SQL NULL
+        expected.extend_from_slice(&[0, 0, 0, 5, 1]);
+        expected.extend_from_slice(b"null");
+
+        // End of the data
+        expected.extend_from_slice(&[0xff, 0xff]);
+        assert_eq!(rows.finish(), expected);
+    }
+}
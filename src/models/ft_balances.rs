@@ -0,0 +1,44 @@
+use crate::models::{FieldCount, RowArguments};
+use bigdecimal::BigDecimal;
+
+/// The row is written as the change of the balance made by one block:
+/// `balance` is the sum of the deltas, `last_event_index` is the last event of the block
+#[derive(Debug, Clone, sqlx::FromRow, FieldCount)]
+pub struct FtBalance {
+    pub contract_account_id: String,
+    pub account_id: String,
+    pub balance: BigDecimal,
+    pub last_event_index: BigDecimal,
+}
+
+// The deltas are applied only once: the block which is already included has the same last_event_index
+const ON_CONFLICT: &str = " ON CONFLICT (contract_account_id, account_id) DO UPDATE SET \
+    balance = ft_balances.balance + excluded.balance, \
+    last_event_index = excluded.last_event_index \
+    WHERE ft_balances.last_event_index < excluded.last_event_index";
+
+impl crate::models::SqlMethods for FtBalance {
+    fn add_to_args(&self, args: &mut impl RowArguments) {
+        args.add(&self.contract_account_id);
+        args.add(&self.account_id);
+        args.add(&self.balance);
+        args.add(&self.last_event_index);
+    }
+
+    fn insert_query(items_count: usize) -> anyhow::Result<String> {
+        Ok("INSERT INTO ft_balances VALUES ".to_owned()
+            + &crate::models::create_placeholders(items_count, FtBalance::field_count())?
+            + ON_CONFLICT)
+    }
+
+    fn insert_from_query(table: &str) -> String {
+        format!(
+            "INSERT INTO ft_balances SELECT * FROM {}{}",
+            table, ON_CONFLICT
+        )
+    }
+
+    fn name() -> String {
+        "ft_balances".to_string()
+    }
+}
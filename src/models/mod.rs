@@ -1,11 +1,19 @@
-use futures::future::try_join_all;
 use std::fmt::Write;
 
+use crate::configs::InsertMethod;
+
 pub use indexer_events::FieldCount;
 
 pub(crate) mod coin_events;
 pub(crate) mod contracts;
+mod copy;
+pub(crate) mod ft_balances;
+pub(crate) mod mt_events;
 pub(crate) mod nft_events;
+pub(crate) mod nft_metadata_updates;
+pub(crate) mod nft_ownership;
+pub(crate) mod raw_events;
+pub(crate) mod rejected_events;
 
 pub trait FieldCount {
     /// Get the number of fields on a struct.
@@ -13,61 +21,55 @@ pub trait FieldCount {
 }
 
 pub trait SqlMethods {
-    fn add_to_args(&self, args: &mut sqlx::postgres::PgArguments);
+    fn add_to_args(&self, args: &mut impl RowArguments);
     fn insert_query(count: usize) -> anyhow::Result<String>;
+    /// The same insert as `insert_query`, but the rows are taken from the given table
+    fn insert_from_query(table: &str) -> String;
     fn name() -> String;
 }
 
-pub async fn chunked_insert<T: SqlMethods + std::fmt::Debug>(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+/// The fields of the row are added one by one in the order of the table columns,
+/// both to the arguments of `INSERT` query and to the rows of `COPY`
+pub trait RowArguments {
+    fn add<'q, T>(&mut self, value: T)
+    where
+        T: 'q + Send + sqlx::Encode<'q, sqlx::Postgres> + sqlx::Type<sqlx::Postgres>;
+}
+
+impl RowArguments for sqlx::postgres::PgArguments {
+    fn add<'q, T>(&mut self, value: T)
+    where
+        T: 'q + Send + sqlx::Encode<'q, sqlx::Postgres> + sqlx::Type<sqlx::Postgres>,
+    {
+        sqlx::Arguments::add(self, value)
+    }
+}
+
+pub async fn insert<T: SqlMethods>(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     items: &[T],
+    insert_method: InsertMethod,
 ) -> anyhow::Result<()> {
-    let futures = items
-        .chunks(crate::db_adapters::CHUNK_SIZE_FOR_BATCH_INSERT)
-        .map(|items_part| insert_retry_or_panic(pool, items_part, crate::db_adapters::RETRY_COUNT));
-    try_join_all(futures).await.map(|_| ())
+    match insert_method {
+        InsertMethod::Insert => chunked_insert(tx, items).await,
+        InsertMethod::Copy => copy::copy_insert(tx, items).await,
+    }
+    .map_err(|err| anyhow::anyhow!("{} were not stored: {}", T::name(), err))
 }
 
-async fn insert_retry_or_panic<T: SqlMethods + std::fmt::Debug>(
-    pool: &sqlx::Pool<sqlx::Postgres>,
+// The chunks are executed one by one in the transaction of the block,
+// the retries are made for the whole block, see `db_adapters::events::store_events`
+async fn chunked_insert<T: SqlMethods>(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
     items: &[T],
-    retry_count: usize,
 ) -> anyhow::Result<()> {
-    let mut interval = crate::INTERVAL;
-    let mut retry_attempt = 0usize;
-    let query = T::insert_query(items.len())?;
-
-    loop {
-        if retry_attempt == retry_count {
-            return Err(anyhow::anyhow!(
-                "Failed to perform query to database after {} attempts. Stop trying.",
-                retry_count
-            ));
-        }
-        retry_attempt += 1;
-
+    for items_part in items.chunks(crate::db_adapters::CHUNK_SIZE_FOR_BATCH_INSERT) {
+        let query = T::insert_query(items_part.len())?;
         let mut args = sqlx::postgres::PgArguments::default();
-        for item in items {
+        for item in items_part {
             item.add_to_args(&mut args);
         }
-
-        match sqlx::query_with(&query, args).execute(pool).await {
-            Ok(_) => break,
-            Err(async_error) => {
-                tracing::warn!(
-                    target: crate::LOGGING_PREFIX,
-                    "Error occurred during {}:\n{} were not stored. \n{:#?} \n Retrying in {} milliseconds...",
-                    async_error,
-                    &T::name(),
-                    &items,
-                    interval.as_millis(),
-                );
-                tokio::time::sleep(interval).await;
-                if interval < crate::MAX_DELAY_TIME {
-                    interval *= 2;
-                }
-            }
-        }
+        sqlx::query_with(&query, args).execute(&mut *tx).await?;
     }
     Ok(())
 }
@@ -1,7 +1,5 @@
+use crate::models::{FieldCount, RowArguments};
 use bigdecimal::BigDecimal;
-use sqlx::Arguments;
-
-use crate::models::FieldCount;
 
 #[derive(Debug, sqlx::FromRow, FieldCount)]
 pub struct NftEvent {
@@ -18,10 +16,12 @@ pub struct NftEvent {
     pub new_owner_account_id: Option<String>,
     pub authorized_account_id: Option<String>,
     pub event_memo: Option<String>,
+    pub event_version: Option<String>,
+    pub unsupported_event_version: bool,
 }
 
 impl crate::models::SqlMethods for NftEvent {
-    fn add_to_args(&self, args: &mut sqlx::postgres::PgArguments) {
+    fn add_to_args(&self, args: &mut impl RowArguments) {
         args.add(&self.event_index);
         args.add(&self.standard);
         args.add(&self.receipt_id);
@@ -35,6 +35,8 @@ impl crate::models::SqlMethods for NftEvent {
         args.add(&self.new_owner_account_id);
         args.add(&self.authorized_account_id);
         args.add(&self.event_memo);
+        args.add(&self.event_version);
+        args.add(self.unsupported_event_version);
     }
 
     fn insert_query(items_count: usize) -> anyhow::Result<String> {
@@ -43,6 +45,13 @@ impl crate::models::SqlMethods for NftEvent {
             + " ON CONFLICT DO NOTHING")
     }
 
+    fn insert_from_query(table: &str) -> String {
+        format!(
+            "INSERT INTO nft_events SELECT * FROM {} ON CONFLICT DO NOTHING",
+            table
+        )
+    }
+
     fn name() -> String {
         "nft_events".to_string()
     }
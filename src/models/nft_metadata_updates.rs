@@ -0,0 +1,52 @@
+use crate::models::{FieldCount, RowArguments};
+use bigdecimal::BigDecimal;
+
+#[derive(Debug, sqlx::FromRow, FieldCount)]
+pub struct NftMetadataUpdate {
+    pub event_index: BigDecimal,
+    pub standard: String,
+    pub receipt_id: String,
+    pub block_height: BigDecimal,
+    pub block_timestamp: BigDecimal,
+    pub contract_account_id: String,
+    pub token_id: Option<String>,
+    pub cause: String,
+    pub status: String,
+    pub event_memo: Option<String>,
+    pub event_version: Option<String>,
+    pub unsupported_event_version: bool,
+}
+
+impl crate::models::SqlMethods for NftMetadataUpdate {
+    fn add_to_args(&self, args: &mut impl RowArguments) {
+        args.add(&self.event_index);
+        args.add(&self.standard);
+        args.add(&self.receipt_id);
+        args.add(&self.block_height);
+        args.add(&self.block_timestamp);
+        args.add(&self.contract_account_id);
+        args.add(&self.token_id);
+        args.add(&self.cause);
+        args.add(&self.status);
+        args.add(&self.event_memo);
+        args.add(&self.event_version);
+        args.add(self.unsupported_event_version);
+    }
+
+    fn insert_query(items_count: usize) -> anyhow::Result<String> {
+        Ok("INSERT INTO nft_metadata_updates VALUES ".to_owned()
+            + &crate::models::create_placeholders(items_count, NftMetadataUpdate::field_count())?
+            + " ON CONFLICT DO NOTHING")
+    }
+
+    fn insert_from_query(table: &str) -> String {
+        format!(
+            "INSERT INTO nft_metadata_updates SELECT * FROM {} ON CONFLICT DO NOTHING",
+            table
+        )
+    }
+
+    fn name() -> String {
+        "nft_metadata_updates".to_string()
+    }
+}
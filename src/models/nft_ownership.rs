@@ -0,0 +1,42 @@
+use crate::models::{FieldCount, RowArguments};
+use bigdecimal::BigDecimal;
+
+#[derive(Debug, Clone, sqlx::FromRow, FieldCount)]
+pub struct NftOwnership {
+    pub contract_account_id: String,
+    pub token_id: String,
+    pub owner_account_id: Option<String>,
+    pub last_event_index: BigDecimal,
+}
+
+// Only the newer event wins, so the blocks could be stored in any order and any number of times
+const ON_CONFLICT: &str = " ON CONFLICT (contract_account_id, token_id) DO UPDATE SET \
+    owner_account_id = excluded.owner_account_id, \
+    last_event_index = excluded.last_event_index \
+    WHERE nft_ownership.last_event_index < excluded.last_event_index";
+
+impl crate::models::SqlMethods for NftOwnership {
+    fn add_to_args(&self, args: &mut impl RowArguments) {
+        args.add(&self.contract_account_id);
+        args.add(&self.token_id);
+        args.add(&self.owner_account_id);
+        args.add(&self.last_event_index);
+    }
+
+    fn insert_query(items_count: usize) -> anyhow::Result<String> {
+        Ok("INSERT INTO nft_ownership VALUES ".to_owned()
+            + &crate::models::create_placeholders(items_count, NftOwnership::field_count())?
+            + ON_CONFLICT)
+    }
+
+    fn insert_from_query(table: &str) -> String {
+        format!(
+            "INSERT INTO nft_ownership SELECT * FROM {}{}",
+            table, ON_CONFLICT
+        )
+    }
+
+    fn name() -> String {
+        "nft_ownership".to_string()
+    }
+}
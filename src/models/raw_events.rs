@@ -0,0 +1,50 @@
+use crate::models::{FieldCount, RowArguments};
+use bigdecimal::BigDecimal;
+
+#[derive(Debug, sqlx::FromRow, FieldCount)]
+pub struct RawEvent {
+    pub event_index: BigDecimal,
+    pub standard: String,
+    pub version: String,
+    pub event: String,
+    pub data: Option<serde_json::Value>,
+    pub receipt_id: String,
+    pub block_height: BigDecimal,
+    pub block_timestamp: BigDecimal,
+    pub contract_account_id: String,
+    pub status: String,
+    pub log_index: i32,
+}
+
+impl crate::models::SqlMethods for RawEvent {
+    fn add_to_args(&self, args: &mut impl RowArguments) {
+        args.add(&self.event_index);
+        args.add(&self.standard);
+        args.add(&self.version);
+        args.add(&self.event);
+        args.add(&self.data);
+        args.add(&self.receipt_id);
+        args.add(&self.block_height);
+        args.add(&self.block_timestamp);
+        args.add(&self.contract_account_id);
+        args.add(&self.status);
+        args.add(self.log_index);
+    }
+
+    fn insert_query(items_count: usize) -> anyhow::Result<String> {
+        Ok("INSERT INTO raw_events VALUES ".to_owned()
+            + &crate::models::create_placeholders(items_count, RawEvent::field_count())?
+            + " ON CONFLICT DO NOTHING")
+    }
+
+    fn insert_from_query(table: &str) -> String {
+        format!(
+            "INSERT INTO raw_events SELECT * FROM {} ON CONFLICT DO NOTHING",
+            table
+        )
+    }
+
+    fn name() -> String {
+        "raw_events".to_string()
+    }
+}
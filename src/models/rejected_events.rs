@@ -0,0 +1,42 @@
+use crate::models::{FieldCount, RowArguments};
+use bigdecimal::BigDecimal;
+
+#[derive(Debug, sqlx::FromRow, FieldCount)]
+pub struct RejectedEvent {
+    pub receipt_id: String,
+    pub log_index: i32,
+    pub block_height: BigDecimal,
+    pub block_timestamp: BigDecimal,
+    pub contract_account_id: String,
+    pub log: String,
+    pub error: String,
+}
+
+impl crate::models::SqlMethods for RejectedEvent {
+    fn add_to_args(&self, args: &mut impl RowArguments) {
+        args.add(&self.receipt_id);
+        args.add(self.log_index);
+        args.add(&self.block_height);
+        args.add(&self.block_timestamp);
+        args.add(&self.contract_account_id);
+        args.add(&self.log);
+        args.add(&self.error);
+    }
+
+    fn insert_query(items_count: usize) -> anyhow::Result<String> {
+        Ok("INSERT INTO rejected_events VALUES ".to_owned()
+            + &crate::models::create_placeholders(items_count, RejectedEvent::field_count())?
+            + " ON CONFLICT DO NOTHING")
+    }
+
+    fn insert_from_query(table: &str) -> String {
+        format!(
+            "INSERT INTO rejected_events SELECT * FROM {} ON CONFLICT DO NOTHING",
+            table
+        )
+    }
+
+    fn name() -> String {
+        "rejected_events".to_string()
+    }
+}
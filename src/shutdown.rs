@@ -0,0 +1,37 @@
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+use crate::LOGGING_PREFIX;
+
+/// Notifies all the receivers when SIGINT or SIGTERM is received
+pub(crate) fn init_shutdown_signal() -> anyhow::Result<watch::Receiver<bool>> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let (sender, receiver) = watch::channel(false);
+
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {},
+            _ = sigterm.recv() => {},
+        }
+        tracing::info!(
+            target: LOGGING_PREFIX,
+            "Shutdown signal received, finishing in-flight blocks..."
+        );
+        // All the receivers could be already dropped, nobody needs the signal then
+        let _ = sender.send(true);
+    });
+    Ok(receiver)
+}
+
+pub(crate) fn is_shutdown_requested(shutdown: &watch::Receiver<bool>) -> bool {
+    *shutdown.borrow()
+}
+
+pub(crate) async fn wait_for_shutdown(mut shutdown: watch::Receiver<bool>) {
+    while !*shutdown.borrow() {
+        if shutdown.changed().await.is_err() {
+            // The sender is dropped without sending the signal, it will never come
+            futures::future::pending::<()>().await;
+        }
+    }
+}
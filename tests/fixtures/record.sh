@@ -0,0 +1,19 @@
+#!/usr/bin/env bash
+# Records one shard of the block from NEAR Lake as the test fixture:
+#   tests/fixtures/record.sh <block_height> <shard_id> <fixture_name> [mainnet|testnet]
+# NEAR Lake buckets are requester-pays, so AWS credentials are needed
+set -euo pipefail
+
+block_height=$(printf "%012d" "$1")
+shard_id=$2
+fixture="$(dirname "$0")/$3.json"
+bucket="near-lake-data-${4:-mainnet}"
+
+tmp_dir=$(mktemp -d)
+trap 'rm -rf "$tmp_dir"' EXIT
+for file in block.json "shard_${shard_id}.json"; do
+    aws s3 cp --request-payer requester --region eu-central-1 \
+        "s3://${bucket}/${block_height}/${file}" "${tmp_dir}/${file}"
+done
+jq --slurpfile shard "${tmp_dir}/shard_${shard_id}.json" \
+    '{block_header: .header, shard: $shard[0]}' "${tmp_dir}/block.json" > "$fixture"